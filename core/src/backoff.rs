@@ -0,0 +1,144 @@
+use rand::{self, Rng};
+
+use std::cmp;
+use std::time::Duration;
+
+
+/// Decides how long to wait before the next attempt of an operation that keeps failing.
+pub trait BackoffPolicy: Send + 'static {
+    /// Returns the time to wait before the given attempt, counting from zero for the first attempt
+    /// after a success. Returns `None` to give up.
+    fn delay(&mut self, attempt: u32) -> Option<Duration>;
+}
+
+/// Waits the same amount of time before every attempt.
+#[derive(Debug, Clone)]
+pub struct FixedBackoff {
+    delay: Duration,
+    max_attempts: Option<u32>,
+}
+
+impl FixedBackoff {
+    /// Constructs a new policy waiting the given time before every attempt, without ever giving up.
+    pub fn new(delay: Duration) -> Self {
+        FixedBackoff {
+            delay,
+            max_attempts: None,
+        }
+    }
+
+    /// Gives up after the given number of attempts.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+}
+
+impl BackoffPolicy for FixedBackoff {
+    fn delay(&mut self, attempt: u32) -> Option<Duration> {
+        match self.max_attempts {
+            Some(max_attempts) if attempt >= max_attempts => None,
+            _ => Some(self.delay),
+        }
+    }
+}
+
+/// Doubles the time to wait with every attempt, starting at an initial delay and never waiting
+/// longer than a maximum delay.
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_attempts: Option<u32>,
+    jitter: bool,
+}
+
+impl ExponentialBackoff {
+    /// Constructs a new exponential backoff policy, without ever giving up.
+    pub fn new(initial_delay: Duration, max_delay: Duration) -> Self {
+        ExponentialBackoff {
+            initial_delay,
+            max_delay,
+            max_attempts: None,
+            jitter: false,
+        }
+    }
+
+    /// Gives up after the given number of attempts.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Randomizes every delay to somewhere between half of it and all of it, so that clients
+    /// failing at the same time don't all try again at the same time.
+    pub fn jitter(mut self) -> Self {
+        self.jitter = true;
+        self
+    }
+}
+
+impl BackoffPolicy for ExponentialBackoff {
+    fn delay(&mut self, attempt: u32) -> Option<Duration> {
+        if let Some(max_attempts) = self.max_attempts {
+            if attempt >= max_attempts {
+                return None;
+            }
+        }
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::max_value());
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay);
+        let delay = cmp::min(delay, self.max_delay);
+        if self.jitter {
+            Some(jittered(delay))
+        } else {
+            Some(delay)
+        }
+    }
+}
+
+fn jittered(delay: Duration) -> Duration {
+    let nanos = delay.as_secs() * 1_000_000_000 + u64::from(delay.subsec_nanos());
+    let nanos = nanos / 2 + rand::thread_rng().gen_range(0, nanos / 2 + 1);
+    Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_backoff() {
+        let mut backoff = FixedBackoff::new(Duration::from_millis(100)).max_attempts(2);
+        assert_eq!(backoff.delay(0), Some(Duration::from_millis(100)));
+        assert_eq!(backoff.delay(1), Some(Duration::from_millis(100)));
+        assert_eq!(backoff.delay(2), None);
+
+        let mut backoff = FixedBackoff::new(Duration::from_millis(100));
+        assert_eq!(backoff.delay(1000), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let mut backoff =
+            ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(1))
+                .max_attempts(10);
+        assert_eq!(backoff.delay(0), Some(Duration::from_millis(100)));
+        assert_eq!(backoff.delay(1), Some(Duration::from_millis(200)));
+        assert_eq!(backoff.delay(4), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.delay(9), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.delay(10), None);
+    }
+
+    #[test]
+    fn jittered_backoff() {
+        let mut backoff =
+            ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(1)).jitter();
+        for _ in 0..100 {
+            let delay = backoff.delay(1).unwrap();
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+    }
+}
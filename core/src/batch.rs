@@ -0,0 +1,95 @@
+use super::{
+    await_rpc_response, serialize_parameters, ClientHandle, Error, ErrorKind, OutgoingMessage,
+};
+
+use futures::future::{self, Either};
+use futures::sync::oneshot;
+use futures::{Future, Sink};
+use serde;
+
+
+/// A builder collecting method calls and notifications that will be sent to the server as a single
+/// JSON-RPC 2.0 batch request. Every entry returns its own future, which resolves independently of
+/// the other entries once the batch has been sent with [`send`](#method.send) and the server has
+/// replied to that specific entry.
+///
+/// If the batch is dropped without being sent, all the futures of its entries will fail.
+#[derive(Debug)]
+#[must_use]
+pub struct BatchRequest {
+    client: ClientHandle,
+    entries: Vec<OutgoingMessage>,
+}
+
+impl BatchRequest {
+    pub(crate) fn new(client: ClientHandle) -> Self {
+        BatchRequest {
+            client,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a method call to the batch and creates a future representing the call's result.
+    pub fn call_method<T>(
+        &mut self,
+        method: impl Into<String>,
+        parameters: &impl serde::Serialize,
+    ) -> impl Future<Item = T, Error = Error> + 'static
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let added = serialize_parameters(parameters).map(|params| {
+            self.entries
+                .push(OutgoingMessage::RpcCall(method.into(), params, tx, None))
+        });
+
+        future::result(added).and_then(|_| await_rpc_response(rx))
+    }
+
+    /// Adds a notification to the batch. The returned future resolves once the transport has
+    /// accepted and flushed the batch.
+    pub fn send_notification(
+        &mut self,
+        method: impl Into<String>,
+        parameters: &impl serde::Serialize,
+    ) -> impl Future<Item = (), Error = Error> + 'static {
+        let (tx, rx) = oneshot::channel();
+        let added = serialize_parameters(parameters).map(|params| {
+            self.entries
+                .push(OutgoingMessage::Notification(method.into(), params, tx))
+        });
+
+        future::result(added)
+            .and_then(|_| rx.map_err(|_| Error::from(ErrorKind::Shutdown)))
+            .flatten()
+    }
+
+    /// Returns the number of calls and notifications in the batch.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if nothing has been added to the batch yet.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sends the batch to the client. The returned future resolves once the client has received
+    /// the batch, the results of the individual entries are delivered through their own futures.
+    /// An empty batch is never sent to the server.
+    pub fn send(self) -> impl Future<Item = (), Error = Error> {
+        if self.entries.is_empty() {
+            return Either::A(future::ok(()));
+        }
+
+        Either::B(
+            self.client
+                .client_handle_tx
+                .clone()
+                .send(OutgoingMessage::Batch(self.entries))
+                .map(|_| ())
+                .map_err(|_| ErrorKind::Shutdown.into()),
+        )
+    }
+}
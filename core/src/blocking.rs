@@ -0,0 +1,150 @@
+use super::{
+    server, Client, ClientHandle, DuplexTransport, Error, ErrorKind, Result, ResultExt, Transport,
+};
+
+use futures::future::Either;
+use futures::sync::oneshot;
+use futures::Future;
+use serde;
+use tokio::runtime::current_thread::Runtime;
+
+use std::fmt;
+use std::sync::mpsc;
+use std::thread;
+
+
+/// A client running on its own background thread, for making calls from synchronous code without
+/// dealing with an executor.
+///
+/// The `Client` future is driven by a single threaded Tokio runtime on the background thread,
+/// which also provides the timer used for timeouts, retries and rate limiting. Calls block the
+/// calling thread until the response arrives.
+///
+/// Dropping the `BlockingClient` shuts the client down and waits for the background thread to
+/// finish. Calls still in flight, also on clones of the [`handle`](#method.handle), then fail with
+/// `ErrorKind::Shutdown`.
+///
+/// ```rust,ignore
+/// let client = BlockingClient::new(transport)?;
+/// let sum: u64 = client.call("add", &(1, 2))?;
+/// ```
+pub struct BlockingClient {
+    handle: ClientHandle,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    thread: Option<thread::JoinHandle<Result<()>>>,
+}
+
+impl BlockingClient {
+    /// Creates a client for the given transport and starts it on a background thread.
+    pub fn new<T: Transport + 'static>(transport: T) -> Result<Self> {
+        Self::spawn(move || transport.into_client())
+    }
+
+    /// Creates a client for the given transport and server handler, and starts it on a background
+    /// thread.
+    pub fn with_server<T, S>(transport: T, server_handler: S) -> Result<Self>
+    where
+        T: DuplexTransport + 'static,
+        S: server::ServerHandler + Send + 'static,
+    {
+        Self::spawn(move || transport.with_server(server_handler))
+    }
+
+    /// Starts the client returned by `create_client` on a background thread. The client is created
+    /// on the background thread, so it can be configured, and its transport can use the reactor of
+    /// the runtime on that thread.
+    pub fn spawn<F, T, S>(create_client: F) -> Result<Self>
+    where
+        F: FnOnce() -> (Client<T, S>, ClientHandle) + Send + 'static,
+        T: Transport + 'static,
+        S: server::ServerHandler + 'static,
+    {
+        let (handle_tx, handle_rx) = mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let thread = thread::Builder::new()
+            .name("jsonrpc-client".to_owned())
+            .spawn(move || -> Result<()> {
+                let mut runtime = match Runtime::new() {
+                    Ok(runtime) => runtime,
+                    Err(error) => {
+                        let error = Error::with_chain(error, ErrorKind::ExecutorError);
+                        let _ = handle_tx.send(Err(error));
+                        return Ok(());
+                    }
+                };
+                let (client, handle) = create_client();
+                let _ = handle_tx.send(Ok(handle));
+                let shutdown = shutdown_rx.then(|_| Ok(()));
+                match runtime.block_on(client.select2(shutdown)) {
+                    Ok(_) => Ok(()),
+                    Err(Either::A((error, _))) => Err(error),
+                    Err(Either::B(((), _))) => unreachable!("Shutdown never fails"),
+                }
+            })
+            .chain_err(|| ErrorKind::ExecutorError)?;
+
+        match handle_rx.recv() {
+            Ok(Ok(handle)) => Ok(BlockingClient {
+                handle,
+                shutdown_tx: Some(shutdown_tx),
+                thread: Some(thread),
+            }),
+            Ok(Err(error)) => Err(error),
+            Err(_) => bail!(ErrorKind::ExecutorError),
+        }
+    }
+
+    /// Returns the handle of the client, for use with the structs generated by
+    /// `jsonrpc_client!`. The futures they return can be waited on from any thread.
+    pub fn handle(&self) -> &ClientHandle {
+        &self.handle
+    }
+
+    /// Calls a method and blocks until the server replied.
+    pub fn call<T>(
+        &self,
+        method: impl Into<String> + 'static,
+        params: &impl serde::Serialize,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        self.handle.call_method(method, params).wait()
+    }
+
+    /// Sends a notification and blocks until it has been sent.
+    pub fn notify(&self, method: impl Into<String>, params: &impl serde::Serialize) -> Result<()> {
+        self.handle.send_notification(method.into(), params).wait()
+    }
+
+    /// Shuts the client down and waits for the background thread to finish. Returns the error the
+    /// client failed with, if it failed before being shut down.
+    pub fn shutdown(mut self) -> Result<()> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            let _ = shutdown_tx.send(());
+        }
+        match self.thread.take().map(|thread| thread.join()) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => bail!(ErrorKind::ExecutorError),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for BlockingClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BlockingClient")
+    }
+}
+
+impl Drop for BlockingClient {
+    fn drop(&mut self) {
+        if let Err(error) = self.stop() {
+            error!("Blocking client failed: {}", error);
+        }
+    }
+}
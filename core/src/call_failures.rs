@@ -0,0 +1,52 @@
+use super::{Error, ErrorKind};
+
+use futures::sync::mpsc;
+use jsonrpc_core::types::{Call, Id, Request};
+use serde_json;
+
+
+/// Lets a transport fail single method calls it could not get a response to, without failing as
+/// a whole. The client hands one to its transport through `Transport::set_call_failures` before
+/// splitting it up with `Transport::io_pair`. The failed calls then resolve to the given errors
+/// directly, without passing through the middleware of the client.
+#[derive(Debug, Clone)]
+pub struct CallFailures(mpsc::UnboundedSender<(Id, Error)>);
+
+impl CallFailures {
+    /// Creates a new way to fail calls, and the receiver the client takes the failures from.
+    pub(crate) fn new() -> (Self, mpsc::UnboundedReceiver<(Id, Error)>) {
+        let (tx, rx) = mpsc::unbounded();
+        (CallFailures(tx), rx)
+    }
+
+    /// Fails the method call with the given id.
+    pub fn fail(&self, id: Id, error: Error) {
+        if self.0.unbounded_send((id, error)).is_err() {
+            trace!("Client dropped already, unable to fail a call");
+        }
+    }
+
+    /// Fails all method calls in a payload the transport was given to send, each with an error of
+    /// the given kind. Notifications in the payload are left alone.
+    pub fn fail_payload<F>(&self, payload: &str, error: F)
+    where
+        F: Fn() -> ErrorKind,
+    {
+        let calls = match serde_json::from_str(payload) {
+            Ok(Request::Single(call)) => vec![call],
+            Ok(Request::Batch(calls)) => calls,
+            Err(e) => {
+                error!("Unable to find the calls in a failed payload: {}", e);
+                return;
+            }
+        };
+        for call in calls {
+            if let Call::MethodCall(method_call) = call {
+                // JSON-RPC 1.0 notifications are method calls with a null id.
+                if method_call.id != Id::Null {
+                    self.fail(method_call.id, error().into());
+                }
+            }
+        }
+    }
+}
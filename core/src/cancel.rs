@@ -0,0 +1,52 @@
+use jsonrpc_core::types::{Id, Params};
+use serde_json::{self, Map, Value as JsonValue};
+
+use std::fmt;
+
+
+/// Method name of the notification used by the Language Server Protocol to cancel requests.
+pub const LSP_CANCEL_METHOD: &str = "$/cancelRequest";
+
+type NotificationBuilder = Box<dyn Fn(&Id) -> (String, Option<Params>) + Send>;
+
+/// Describes the notification a client sends to the server when the future of a method call is
+/// dropped before the server replied to it. This allows the server to stop working on requests
+/// nobody is waiting for anymore.
+pub struct CancelNotification {
+    builder: NotificationBuilder,
+}
+
+impl CancelNotification {
+    /// Creates a cancel notification from a function that, given the id of the cancelled call,
+    /// returns the method name and parameters of the notification to send.
+    pub fn new<F>(builder: F) -> Self
+    where
+        F: Fn(&Id) -> (String, Option<Params>) + Send + 'static,
+    {
+        CancelNotification {
+            builder: Box::new(builder),
+        }
+    }
+
+    /// Creates a Language Server Protocol style cancel notification. The notification invokes
+    /// `$/cancelRequest` with the id of the cancelled call as the `id` parameter.
+    pub fn lsp() -> Self {
+        Self::new(|id| {
+            let mut params = Map::new();
+            let id = serde_json::to_value(id).unwrap_or(JsonValue::Null);
+            params.insert("id".to_owned(), id);
+            (LSP_CANCEL_METHOD.to_owned(), Some(Params::Map(params)))
+        })
+    }
+
+    /// Builds the method name and parameters of the notification cancelling the given call.
+    pub(crate) fn build(&self, id: &Id) -> (String, Option<Params>) {
+        (self.builder)(id)
+    }
+}
+
+impl fmt::Debug for CancelNotification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CancelNotification")
+    }
+}
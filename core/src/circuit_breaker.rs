@@ -0,0 +1,454 @@
+use super::{Error, ErrorKind, Result};
+use rpc_error::RpcErrorExt;
+
+use futures::future;
+use futures::Future;
+use serde_json::Value as JsonValue;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+
+type FailurePredicate = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+type StateListener = Arc<dyn Fn(CircuitState, CircuitState) + Send + Sync>;
+
+/// The state of a `CircuitBreaker`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls are sent to the server, and their outcomes are recorded.
+    Closed,
+    /// Too many calls failed. Calls fail with `ErrorKind::CircuitOpen` without being sent.
+    Open,
+    /// The cool-down period is over. A limited number of probe calls are sent to find out if the
+    /// server has recovered, all other calls fail with `ErrorKind::CircuitOpen`.
+    HalfOpen,
+}
+
+/// Stops sending method calls to a server that keeps failing them, so that callers fail fast
+/// instead of waiting for the server to time out.
+///
+/// The breaker starts out closed, recording the outcomes of the latest calls. Once the share of
+/// failed calls among them reaches the failure rate threshold, the breaker opens. After the
+/// cool-down period it lets a number of probe calls through. If all of them succeed, the breaker
+/// closes again, otherwise it opens for another cool-down period.
+///
+/// Clones of a breaker share the same state and settings, including the failure predicate and the
+/// state listener, so the same breaker can guard several client handles.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    inner: Arc<Mutex<Breaker>>,
+}
+
+struct Breaker {
+    failure_rate_threshold: f64,
+    window_size: usize,
+    minimum_calls: usize,
+    cool_down: Duration,
+    probe_calls: usize,
+    state: State,
+    outcomes: VecDeque<bool>,
+    is_failure: FailurePredicate,
+    listener: Option<StateListener>,
+}
+
+enum State {
+    Closed,
+    Open(Instant),
+    HalfOpen { in_flight: usize, successes: usize },
+}
+
+impl CircuitBreaker {
+    /// Constructs a new closed circuit breaker. By default the breaker opens when half of the
+    /// latest 20 calls failed, with at least 10 calls recorded, and stays open for 30 seconds
+    /// before sending a single probe call. Transport and HTTP errors, timeouts, lost connections,
+    /// a shut down client and JSON-RPC 2.0 internal and implementation-defined server errors count
+    /// as failures.
+    pub fn new() -> Self {
+        CircuitBreaker {
+            inner: Arc::new(Mutex::new(Breaker {
+                failure_rate_threshold: 0.5,
+                window_size: 20,
+                minimum_calls: 10,
+                cool_down: Duration::from_secs(30),
+                probe_calls: 1,
+                state: State::Closed,
+                outcomes: VecDeque::new(),
+                is_failure: Arc::new(is_server_failure),
+                listener: None,
+            })),
+        }
+    }
+
+    /// Sets the share of failed calls, between 0 and 1, at which the breaker opens.
+    pub fn failure_rate_threshold(self, threshold: f64) -> Self {
+        self.lock().failure_rate_threshold = threshold;
+        self
+    }
+
+    /// Sets the number of latest calls the failure rate is computed over.
+    pub fn window_size(self, window_size: usize) -> Self {
+        self.lock().window_size = window_size;
+        self
+    }
+
+    /// Sets the number of calls that have to be recorded before the breaker can open.
+    pub fn minimum_calls(self, minimum_calls: usize) -> Self {
+        self.lock().minimum_calls = minimum_calls;
+        self
+    }
+
+    /// Sets for how long the breaker stays open before sending probe calls.
+    pub fn cool_down(self, cool_down: Duration) -> Self {
+        self.lock().cool_down = cool_down;
+        self
+    }
+
+    /// Sets the number of probe calls that have to succeed for the breaker to close again.
+    pub fn probe_calls(self, probe_calls: usize) -> Self {
+        self.lock().probe_calls = probe_calls;
+        self
+    }
+
+    /// Sets the predicate deciding which errors count as failures of the server. Errors that don't
+    /// count, such as invalid parameters, are recorded as successful calls.
+    pub fn failure_if<F>(self, is_failure: F) -> Self
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        self.lock().is_failure = Arc::new(is_failure);
+        self
+    }
+
+    /// Sets a function that is called with the old and the new state whenever the state of the
+    /// breaker changes.
+    pub fn on_state_change<F>(self, listener: F) -> Self
+    where
+        F: Fn(CircuitState, CircuitState) + Send + Sync + 'static,
+    {
+        self.lock().listener = Some(Arc::new(listener));
+        self
+    }
+
+    /// Returns the current state of the breaker.
+    pub fn state(&self) -> CircuitState {
+        self.lock().state()
+    }
+
+    /// Sends a call through the breaker. Fails with `ErrorKind::CircuitOpen` without sending the
+    /// call if the breaker does not let it through.
+    pub(crate) fn call<F>(&self, call: F) -> impl Future<Item = JsonValue, Error = Error>
+    where
+        F: Future<Item = JsonValue, Error = Error>,
+    {
+        let breaker = self.clone();
+        future::lazy(move || breaker.acquire()).and_then(move |permit| {
+            call.then(move |result| {
+                permit.record(&result);
+                result
+            })
+        })
+    }
+
+    fn acquire(self) -> Result<Permit> {
+        let (old_state, acquired, new_state) = {
+            let mut breaker = self.lock();
+            let old_state = breaker.state();
+            let acquired = breaker.acquire(Instant::now());
+            (old_state, acquired, breaker.state())
+        };
+        self.notify(old_state, new_state);
+        if !acquired {
+            bail!(ErrorKind::CircuitOpen);
+        }
+        Ok(Permit {
+            breaker: self,
+            recorded: false,
+        })
+    }
+
+    fn release(&self, outcome: Option<bool>) {
+        let (old_state, new_state) = {
+            let mut breaker = self.lock();
+            let old_state = breaker.state();
+            breaker.release(outcome, Instant::now());
+            (old_state, breaker.state())
+        };
+        self.notify(old_state, new_state);
+    }
+
+    fn notify(&self, old_state: CircuitState, new_state: CircuitState) {
+        if old_state != new_state {
+            debug!(
+                "Circuit breaker changed from {:?} to {:?}",
+                old_state, new_state
+            );
+            // The listener is called without holding the lock, so that it can inspect the breaker.
+            let listener = self.lock().listener.clone();
+            if let Some(listener) = listener {
+                listener(old_state, new_state);
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Breaker> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Returns true if the error means that the server is down or failing, rather than that something
+/// was wrong with the call.
+fn is_server_failure(error: &Error) -> bool {
+    match *error.kind() {
+        ErrorKind::TransportError
+        | ErrorKind::HttpError(_)
+        | ErrorKind::Timeout
+        | ErrorKind::ConnectionLost
+        | ErrorKind::Shutdown => true,
+        ErrorKind::JsonRpcError(ref error) => {
+            error.is_internal_error() || error.is_server_error_range()
+        }
+        _ => false,
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+impl Breaker {
+    fn state(&self) -> CircuitState {
+        match self.state {
+            State::Closed => CircuitState::Closed,
+            State::Open(_) => CircuitState::Open,
+            State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Decides if a call may be sent now.
+    fn acquire(&mut self, now: Instant) -> bool {
+        if let State::Open(until) = self.state {
+            if now < until {
+                return false;
+            }
+            self.state = State::HalfOpen {
+                in_flight: 0,
+                successes: 0,
+            };
+        }
+        match self.state {
+            State::HalfOpen {
+                ref mut in_flight,
+                successes,
+            } => {
+                if *in_flight + successes >= self.probe_calls {
+                    return false;
+                }
+                *in_flight += 1;
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// Records the outcome of a call that was let through, `true` meaning that it failed. An
+    /// outcome of `None` means that the call was dropped before it finished.
+    fn release(&mut self, outcome: Option<bool>, now: Instant) {
+        match self.state {
+            State::Closed => {
+                if let Some(failed) = outcome {
+                    self.outcomes.push_back(failed);
+                    while self.outcomes.len() > self.window_size {
+                        self.outcomes.pop_front();
+                    }
+                    if self.should_open() {
+                        self.open(now);
+                    }
+                }
+            }
+            State::HalfOpen {
+                in_flight,
+                successes,
+            } => {
+                let in_flight = in_flight.saturating_sub(1);
+                self.state = match outcome {
+                    Some(true) => State::Open(now + self.cool_down),
+                    Some(false) if successes + 1 >= self.probe_calls => State::Closed,
+                    Some(false) => State::HalfOpen {
+                        in_flight,
+                        successes: successes + 1,
+                    },
+                    None => State::HalfOpen {
+                        in_flight,
+                        successes,
+                    },
+                };
+            }
+            // Calls sent before the breaker opened don't change anything.
+            State::Open(_) => (),
+        }
+    }
+
+    fn should_open(&self) -> bool {
+        let calls = self.outcomes.len();
+        if calls == 0 || calls < self.minimum_calls {
+            return false;
+        }
+        let failures = self.outcomes.iter().filter(|failed| **failed).count();
+        failures as f64 / calls as f64 >= self.failure_rate_threshold
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.state = State::Open(now + self.cool_down);
+        self.outcomes.clear();
+    }
+}
+
+/// A call let through by the breaker. Dropping it without recording an outcome frees the slot of a
+/// probe call.
+struct Permit {
+    breaker: CircuitBreaker,
+    recorded: bool,
+}
+
+impl Permit {
+    fn record(mut self, result: &Result<JsonValue>) {
+        let failed = match *result {
+            Ok(_) => false,
+            Err(ref error) => {
+                let is_failure = self.breaker.lock().is_failure.clone();
+                is_failure(error)
+            }
+        };
+        self.recorded = true;
+        self.breaker.release(Some(failed));
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.breaker.release(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpc_error::ErrorCode;
+
+    fn breaker() -> Breaker {
+        Breaker {
+            failure_rate_threshold: 0.5,
+            window_size: 4,
+            minimum_calls: 2,
+            cool_down: Duration::from_secs(10),
+            probe_calls: 1,
+            state: State::Closed,
+            outcomes: VecDeque::new(),
+            is_failure: Arc::new(is_server_failure),
+            listener: None,
+        }
+    }
+
+    #[test]
+    fn opens_on_failure_rate() {
+        let now = Instant::now();
+        let mut breaker = breaker();
+        for _ in 0..3 {
+            assert!(breaker.acquire(now));
+            breaker.release(Some(false), now);
+        }
+        assert!(breaker.acquire(now));
+        breaker.release(Some(true), now);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.acquire(now));
+        breaker.release(Some(true), now);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.acquire(now));
+    }
+
+    #[test]
+    fn probes_after_cool_down() {
+        let now = Instant::now();
+        let later = now + Duration::from_secs(10);
+        let mut breaker = breaker();
+        breaker.open(now);
+
+        assert!(breaker.acquire(later));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(!breaker.acquire(later));
+        breaker.release(Some(true), later);
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let even_later = later + Duration::from_secs(10);
+        assert!(breaker.acquire(even_later));
+        breaker.release(None, even_later);
+        assert!(breaker.acquire(even_later));
+        breaker.release(Some(false), even_later);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn opens_on_transport_failures() {
+        let circuit_breaker = CircuitBreaker::new().window_size(2).minimum_calls(2);
+        for _ in 0..2 {
+            let call = future::err(ErrorKind::TransportError.into());
+            assert!(circuit_breaker.call(call).wait().is_err());
+        }
+        assert_eq!(circuit_breaker.state(), CircuitState::Open);
+        match circuit_breaker.call(future::ok(JsonValue::Null)).wait() {
+            Err(Error(ErrorKind::CircuitOpen, _)) => (),
+            result => panic!("Expected the circuit to be open, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn clones_share_predicate_and_listener() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let circuit_breaker = CircuitBreaker::new().window_size(1).minimum_calls(1);
+        let changes2 = changes.clone();
+        circuit_breaker
+            .clone()
+            .failure_if(|error| error.is_method_not_found())
+            .on_state_change(move |old, new| changes2.lock().unwrap().push((old, new)));
+
+        let call = future::err(ErrorKind::TransportError.into());
+        assert!(circuit_breaker.call(call).wait().is_err());
+        assert_eq!(circuit_breaker.state(), CircuitState::Closed);
+        let error = ::jsonrpc_core::Error::method_not_found();
+        let call = future::err(ErrorKind::JsonRpcError(error).into());
+        assert!(circuit_breaker.call(call).wait().is_err());
+        assert_eq!(circuit_breaker.state(), CircuitState::Open);
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![(CircuitState::Closed, CircuitState::Open)]
+        );
+    }
+
+    #[test]
+    fn counts_server_failures() {
+        let rpc_error = |code| ErrorKind::JsonRpcError(::jsonrpc_core::Error::new(code)).into();
+        assert!(is_server_failure(&ErrorKind::HttpError(503).into()));
+        assert!(is_server_failure(&ErrorKind::ConnectionLost.into()));
+        assert!(is_server_failure(&rpc_error(ErrorCode::InternalError)));
+        assert!(is_server_failure(&rpc_error(ErrorCode::from(-32001))));
+        assert!(!is_server_failure(&rpc_error(ErrorCode::InvalidParams)));
+        assert!(!is_server_failure(&ErrorKind::DeserializeError.into()));
+    }
+}
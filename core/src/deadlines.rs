@@ -0,0 +1,77 @@
+use futures::{Async, Future};
+use jsonrpc_core::types::Id;
+use tokio_timer::{self, Delay};
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+
+/// Keeps track of the points in time at which pending calls should time out. A single timer is
+/// kept for the earliest deadline, so polling the deadlines will wake the current task once that
+/// deadline has passed.
+#[derive(Debug)]
+pub struct Deadlines {
+    deadlines: BTreeMap<Instant, Vec<Id>>,
+    delay: Option<Delay>,
+}
+
+impl Deadlines {
+    pub fn new() -> Self {
+        Deadlines {
+            deadlines: BTreeMap::new(),
+            delay: None,
+        }
+    }
+
+    /// Adds a new deadline for the call with the given id.
+    pub fn insert(&mut self, id: Id, deadline: Instant) {
+        match self.deadlines.entry(deadline) {
+            Entry::Occupied(mut entry) => entry.get_mut().push(id),
+            Entry::Vacant(entry) => {
+                entry.insert(vec![id]);
+            }
+        }
+    }
+
+    /// Removes all deadlines, returning the ids of the calls they belonged to.
+    pub fn drain(&mut self) -> Vec<Id> {
+        self.delay = None;
+        let deadlines = ::std::mem::replace(&mut self.deadlines, BTreeMap::new());
+        deadlines.into_iter().flat_map(|(_, ids)| ids).collect()
+    }
+
+    /// Returns the ids of all calls whose deadline has passed. If there are deadlines left, the
+    /// current task will be notified once the earliest one passes.
+    pub fn poll_expired(&mut self) -> Result<Vec<Id>, tokio_timer::Error> {
+        let mut expired_ids = Vec::new();
+        loop {
+            let now = Instant::now();
+            let expired_deadlines: Vec<Instant> = self
+                .deadlines
+                .range(..=now)
+                .map(|(deadline, _)| *deadline)
+                .collect();
+            for deadline in expired_deadlines {
+                if let Some(ids) = self.deadlines.remove(&deadline) {
+                    expired_ids.extend(ids);
+                }
+            }
+
+            let next_deadline = match self.deadlines.keys().next() {
+                Some(deadline) => *deadline,
+                None => {
+                    self.delay = None;
+                    return Ok(expired_ids);
+                }
+            };
+            let delay = self.delay.get_or_insert_with(|| Delay::new(next_deadline));
+            if delay.deadline() != next_deadline {
+                delay.reset(next_deadline);
+            }
+            if let Async::NotReady = delay.poll()? {
+                return Ok(expired_ids);
+            }
+        }
+    }
+}
@@ -23,3 +23,17 @@ jsonrpc_client!(
         pub fn concat(&mut self, arg0: String, arg1: u64) -> Future<String>;
     }
 );
+
+jsonrpc_server!(
+    /// Just an example trait to showcase how to use the `jsonrpc_server` macro and what the
+    /// resulting traits look like.
+    pub trait ExampleCallbacks {
+        /// A method the server can call on the client. The implementation presumably answers with
+        /// the sum of the two arguments.
+        fn add(&self, arg0: u64, arg1: u64) -> Future<u64>;
+
+        /// A notification the server can send to the client, telling it how far the server has
+        /// come with some task.
+        notify fn progress(&self, percent: u8);
+    }
+);
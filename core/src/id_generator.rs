@@ -1,24 +1,113 @@
 use jsonrpc_core::types::Id;
+use uuid::Uuid;
 
+use std::fmt;
+
+
+/// Generates the ids of requests. An id has to be unique among all requests that are pending at
+/// the same time.
+pub trait IdGenerator: fmt::Debug + Send {
+    /// Returns the id of the next request.
+    fn next_id(&mut self) -> Id;
+}
+
+/// Generates monotonically increasing numeric ids, starting at 1. This is the default id
+/// generator.
 #[derive(Debug)]
-pub struct IdGenerator {
+pub struct NumericIdGenerator {
     next_id: u64,
 }
 
-impl IdGenerator {
-    pub fn new() -> IdGenerator {
-        IdGenerator { next_id: 1 }
-    }
-
-    pub fn next(&mut self) -> Id {
-        let id = Id::Num(self.next_id);
-        self.next_id += 1;
-        id
+impl NumericIdGenerator {
+    /// Constructs a new numeric id generator.
+    pub fn new() -> NumericIdGenerator {
+        NumericIdGenerator { next_id: 1 }
     }
 
+    /// Returns the next id as an integer.
     pub fn next_int(&mut self) -> u64 {
         let id = self.next_id;
         self.next_id += 1;
         id
     }
 }
+
+impl Default for NumericIdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for NumericIdGenerator {
+    fn next_id(&mut self) -> Id {
+        Id::Num(self.next_int())
+    }
+}
+
+/// Generates string ids made of a fixed prefix followed by an increasing number, such as
+/// `"worker-1"`, `"worker-2"` and so on. Clients sharing a connection can use different prefixes
+/// to keep their ids apart.
+#[derive(Debug)]
+pub struct PrefixedIdGenerator {
+    prefix: String,
+    counter: NumericIdGenerator,
+}
+
+impl PrefixedIdGenerator {
+    /// Constructs a new id generator which prepends the given prefix to all ids.
+    pub fn new(prefix: impl Into<String>) -> PrefixedIdGenerator {
+        PrefixedIdGenerator {
+            prefix: prefix.into(),
+            counter: NumericIdGenerator::new(),
+        }
+    }
+}
+
+impl IdGenerator for PrefixedIdGenerator {
+    fn next_id(&mut self) -> Id {
+        Id::Str(format!("{}{}", self.prefix, self.counter.next_int()))
+    }
+}
+
+/// Generates random version 4 UUIDs as string ids. Useful to correlate requests with the logs of
+/// the server.
+#[derive(Debug, Default)]
+pub struct RandomIdGenerator;
+
+impl RandomIdGenerator {
+    /// Constructs a new random id generator.
+    pub fn new() -> RandomIdGenerator {
+        RandomIdGenerator
+    }
+}
+
+impl IdGenerator for RandomIdGenerator {
+    fn next_id(&mut self) -> Id {
+        Id::Str(Uuid::new_v4().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_ids_increase() {
+        let mut generator = NumericIdGenerator::new();
+        assert_eq!(generator.next_id(), Id::Num(1));
+        assert_eq!(generator.next_id(), Id::Num(2));
+    }
+
+    #[test]
+    fn prefixed_ids() {
+        let mut generator = PrefixedIdGenerator::new("worker-");
+        assert_eq!(generator.next_id(), Id::Str("worker-1".to_owned()));
+        assert_eq!(generator.next_id(), Id::Str("worker-2".to_owned()));
+    }
+
+    #[test]
+    fn random_ids_are_unique() {
+        let mut generator = RandomIdGenerator::new();
+        assert_ne!(generator.next_id(), generator.next_id());
+    }
+}
@@ -23,6 +23,13 @@
 //! The main (and so far only) transport implementation is the Hyper based HTTP implementation
 //! in the [`jsonrpc-client-http`](../jsonrpc_client_http/index.html) crate.
 //!
+//! # `std::future`
+//!
+//! The client is built on futures 0.1. With the `std-future` feature, the
+//! [`RpcFuture`s](struct.RpcFuture.html) returned by method calls can also be awaited, transports
+//! built on futures 0.3 can be used through the [`std_future`](std_future/index.html) module, and
+//! the `Client` can be turned into a `std::future::Future` with `Client::into_std_future`.
+//!
 //! # Example
 //!
 //! ```rust,ignore
@@ -57,35 +64,52 @@
 #[macro_use]
 pub extern crate error_chain;
 extern crate futures;
+#[cfg(feature = "std-future")]
+extern crate futures_util;
 extern crate jsonrpc_client_utils;
 extern crate jsonrpc_core;
 #[macro_use]
 extern crate log;
+extern crate rand;
 #[macro_use]
 extern crate serde;
 extern crate serde_json;
+extern crate tokio;
+extern crate tokio_timer;
+extern crate uuid;
 
-use futures::future;
+use futures::future::{self, Either};
 use futures::sync::mpsc;
 pub use futures::sync::oneshot;
 pub use futures::Future;
 use futures::{Async, AsyncSink};
 use futures::{Sink, Stream};
 use jsonrpc_core::types::{
-    Failure as RpcFailure, Id, MethodCall, Notification, Output, Params, Request, Response,
-    Success as RpcSuccess, Version,
+    Call, Failure as RpcFailure, Id, MethodCall, Notification, Output, Params, Request, Response,
+    Success as RpcSuccess,
 };
 use serde_json::Value as JsonValue;
 
 
-use std::collections::HashMap;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Contains the main macro of this crate, `jsonrpc_client`.
 #[macro_use]
 mod macros;
 
-mod id_generator;
-use id_generator::IdGenerator;
+/// Module containing the strategies for generating the ids of requests.
+pub mod id_generator;
+use id_generator::{IdGenerator, NumericIdGenerator};
+
+mod deadlines;
+use deadlines::Deadlines;
+
+mod pending_calls;
+use pending_calls::PendingCalls;
 
 use jsonrpc_client_utils::select_weak::{self, SelectWithWeakExt};
 
@@ -93,7 +117,74 @@ use jsonrpc_client_utils::select_weak::{self, SelectWithWeakExt};
 /// various method and notification requests coming in from the server. Does not work with HTTP.
 pub mod server;
 
-/// Module containing an example client. To show in the docs what a generated struct look like.
+/// Module containing the batch request builder, allowing several method calls and notifications to
+/// be sent to the server as one JSON-RPC 2.0 batch.
+pub mod batch;
+use batch::BatchRequest;
+
+/// Module containing the notifications a client can send to tell the server that the result of a
+/// call is no longer needed.
+pub mod cancel;
+use cancel::CancelNotification;
+
+/// Module containing the middleware trait, allowing one to inspect and alter the requests and
+/// responses passing through a client.
+pub mod middleware;
+use middleware::{CallAction, Middleware, MiddlewareStack};
+
+/// Module containing helpers for inspecting and classifying the JSON-RPC 2.0 errors returned by
+/// the server.
+pub mod rpc_error;
+
+/// Module containing the metrics of the method calls made by a client. Only available with the
+/// `metrics` feature.
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "metrics")]
+use metrics::{CallMetrics, MetricsRecorder};
+
+/// Module containing the policies deciding how long to wait between attempts of failing
+/// operations.
+pub mod backoff;
+
+/// Module containing a transport wrapper that reconnects to the server whenever the connection is
+/// lost.
+pub mod reconnect;
+
+/// Module containing the means for transports to fail single method calls, such as calls pending
+/// on a lost connection, without failing the whole client.
+pub mod call_failures;
+use call_failures::CallFailures;
+
+/// Module containing the policy for retrying failed calls to idempotent methods.
+pub mod retry;
+use retry::RetryPolicy;
+
+/// Module containing the limits on the number and rate of requests sent by a client.
+pub mod rate_limit;
+use rate_limit::{RateLimit, RateLimiter};
+
+/// Module containing the versions of the JSON-RPC protocol a client can speak.
+pub mod protocol;
+use protocol::ProtocolVersion;
+
+/// Module containing a circuit breaker for failing fast while a server keeps failing calls.
+pub mod circuit_breaker;
+use circuit_breaker::CircuitBreaker;
+
+/// Module containing a client running on a background thread, for use from synchronous code.
+pub mod blocking;
+
+mod rpc_future;
+pub use rpc_future::RpcFuture;
+
+/// Module containing the `std::future` counterparts of the transport traits, allowing transports
+/// built on futures 0.3 to be used. Only available with the `std-future` feature.
+#[cfg(feature = "std-future")]
+pub mod std_future;
+
+/// Module containing an example client and callback trait. To show in the docs what the generated
+/// struct and trait look like.
 pub mod example;
 
 error_chain! {
@@ -119,11 +210,38 @@ error_chain! {
         InvalidVersion {
             description("Method call returned a response that was not specified as JSON-RPC 2.0")
         }
+        /// The call did not get a response before its deadline.
+        Timeout {
+            description("The call did not receive a response in time")
+        }
         /// Error when trying to send a new message to the server because the client is already
         /// shut down.
         Shutdown {
             description("RPC Client already shut down")
         }
+        /// The server replied to a batch request, but the reply did not contain a response to this
+        /// call.
+        MissingBatchResponse {
+            description("The batch response did not contain a response for the call")
+        }
+        /// The connection to the server was lost before the call was answered, and the call was
+        /// not sent again on a new connection.
+        ConnectionLost {
+            description("The connection to the server was lost before the call was answered")
+        }
+        /// The HTTP request carrying the call was answered with a status code other than 200 OK.
+        HttpError(status: u16) {
+            description("The server did not return 200 OK")
+            display("Http error. Status code {}", status)
+        }
+        /// The call was not sent because the circuit breaker of the client handle is open.
+        CircuitOpen {
+            description("The circuit breaker is open")
+        }
+        /// Unable to run the event loop of a `BlockingClient`.
+        ExecutorError {
+            description("Unable to run the event loop of the client")
+        }
         /// The request was replied to, but with a JSON-RPC 2.0 error.
         JsonRpcError(error: jsonrpc_core::Error) {
             description("Method call returned JSON-RPC 2.0 error")
@@ -139,24 +257,128 @@ error_chain! {
 #[derive(Debug, Clone)]
 pub struct ClientHandle {
     client_handle_tx: mpsc::Sender<OutgoingMessage>,
+    retry_policy: Option<Arc<RetryPolicy>>,
+    circuit_breaker: Option<CircuitBreaker>,
 }
 
 impl ClientHandle {
+    /// Creates a handle sending its messages on the given channel. Only needed by clients that
+    /// handle the messages themselves, instead of sending them to the server over a `Transport`.
+    pub fn from_sender(client_handle_tx: mpsc::Sender<OutgoingMessage>) -> Self {
+        ClientHandle {
+            client_handle_tx,
+            retry_policy: None,
+            circuit_breaker: None,
+        }
+    }
+
+    /// Sets the policy for retrying failed calls to idempotent methods made through this handle.
+    /// Calls to other methods are never retried.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Sends all method calls made through this handle through the given circuit breaker. While
+    /// the breaker is open, calls fail with `ErrorKind::CircuitOpen` without being sent.
+    pub fn circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
     /// Invokes an RPC and creates a future representing the RPC's result.
     pub fn call_method<T>(
         &self,
         method: impl Into<String> + 'static,
         parameters: &impl serde::Serialize,
-    ) -> impl Future<Item = T, Error = Error> + 'static
+    ) -> RpcFuture<T>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        self.send_method_call(method.into(), serialize_parameters(parameters), None, false)
+    }
+
+    /// Invokes an RPC of a method that is safe to call more than once, and creates a future
+    /// representing the RPC's result. If the call fails, it is retried according to the retry
+    /// policy of this handle.
+    pub fn call_idempotent_method<T>(
+        &self,
+        method: impl Into<String> + 'static,
+        parameters: &impl serde::Serialize,
+    ) -> RpcFuture<T>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        self.send_method_call(method.into(), serialize_parameters(parameters), None, true)
+    }
+
+    /// Invokes an RPC and creates a future representing the RPC's result. If the server has not
+    /// replied within the given timeout, the call fails with `ErrorKind::Timeout`. The timeout
+    /// takes precedence over the default timeout of the client.
+    pub fn call_method_with_timeout<T>(
+        &self,
+        method: impl Into<String> + 'static,
+        parameters: &impl serde::Serialize,
+        timeout: Duration,
+    ) -> RpcFuture<T>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        self.send_method_call(
+            method.into(),
+            serialize_parameters(parameters),
+            Some(timeout),
+            false,
+        )
+    }
+
+    /// Sends a method call with already serialized parameters, retrying it according to the retry
+    /// policy if the method is idempotent. Primarily intended to be used from macro
+    /// `jsonrpc_client!`.
+    #[doc(hidden)]
+    pub fn send_method_call<T>(
+        &self,
+        method: String,
+        params: Result<Option<Params>>,
+        timeout: Option<Duration>,
+        idempotent: bool,
+    ) -> RpcFuture<T>
     where
         T: serde::de::DeserializeOwned + Send + 'static,
     {
-        let (tx, rx) = oneshot::channel();
         let client = self.clone();
+        let retry_policy = self
+            .retry_policy
+            .clone()
+            .filter(|policy| idempotent || policy.is_idempotent(&method));
+
+        let call = future::result(params)
+            .and_then(move |params| match retry_policy {
+                Some(policy) => Either::A(retry::call_with_retries(
+                    client, method, params, timeout, policy,
+                )),
+                None => Either::B(client.send_rpc_call(method, params, timeout)),
+            })
+            .and_then(|result| {
+                serde_json::from_value(result).chain_err(|| ErrorKind::DeserializeError)
+            });
+        RpcFuture::new(call)
+    }
 
-        future::result(serialize_parameters(parameters)).and_then(move |params| {
-            client.send_client_call(Ok(OutgoingMessage::RpcCall(method.into(), params, tx)), rx)
-        })
+    /// Sends a single method call, through the circuit breaker of this handle if it has one.
+    fn send_rpc_call(
+        &self,
+        method: String,
+        params: Option<Params>,
+        timeout: Option<Duration>,
+    ) -> impl Future<Item = JsonValue, Error = Error> + 'static {
+        let (tx, rx) = oneshot::channel();
+        let call = OutgoingMessage::RpcCall(method, params, tx, timeout);
+        let result = self.send_client_call(Ok(call), rx);
+        match self.circuit_breaker {
+            Some(ref circuit_breaker) => Either::A(circuit_breaker.call(result)),
+            None => Either::B(result),
+        }
     }
 
     /// Send arbitrary RPC call to Client. Primarily intended to be used from macro
@@ -171,8 +393,14 @@ impl ClientHandle {
 
         future::result(client_call)
             .and_then(|call| rpc_chan.send(call).map_err(|_| ErrorKind::Shutdown.into()))
-            .and_then(|_| rx.map_err(|_| ErrorKind::Shutdown).flatten())
-            .and_then(|r| serde_json::from_value(r).chain_err(|| ErrorKind::DeserializeError))
+            .and_then(|_| await_rpc_response(rx))
+    }
+
+    /// Creates a builder for a batch request. Method calls and notifications added to the batch
+    /// are sent to the server as a single JSON-RPC 2.0 batch once the batch is sent, but each call
+    /// still resolves to its own result.
+    pub fn batch(&self) -> BatchRequest {
+        BatchRequest::new(self.clone())
     }
 
 
@@ -182,17 +410,30 @@ impl ClientHandle {
         method: String,
         parameters: &impl serde::Serialize,
     ) -> impl Future<Item = (), Error = Error> {
+        self.send_serialized_notification(method, serialize_parameters(parameters))
+    }
+
+    /// Sends a notification with already serialized parameters. The future resolves once the
+    /// transport has accepted and flushed the notification. Primarily intended to be used from
+    /// macro `jsonrpc_client!`.
+    #[doc(hidden)]
+    pub fn send_serialized_notification(
+        &self,
+        method: String,
+        params: Result<Option<Params>>,
+    ) -> RpcFuture<()> {
         let (tx, rx) = oneshot::channel();
 
         let rpc_chan = self.client_handle_tx.clone();
 
-        future::result(serialize_parameters(parameters))
+        let notification = future::result(params)
             .and_then(|params| {
                 rpc_chan
                     .send(OutgoingMessage::Notification(method, params, tx))
                     .map_err(|_| ErrorKind::Shutdown.into())
             }).and_then(|_| rx.map_err(|_| Error::from(ErrorKind::Shutdown)))
-            .flatten()
+            .flatten();
+        RpcFuture::new(notification)
     }
 }
 
@@ -211,6 +452,11 @@ pub trait Transport: Sized + Send{
     /// Transforms the transport implementation into a sink and a stream.
     fn io_pair(self) -> (Self::Sink, Self::Stream);
 
+    /// Gives the transport the means to fail single method calls it could not get a response to,
+    /// without failing the whole client. Called by the client before `io_pair`. Transports that
+    /// only ever fail as a whole can ignore it.
+    fn set_call_failures(&mut self, _call_failures: CallFailures) {}
+
     /// Creates a Client and a ClientHandle from a transport implementation.
     fn into_client(self) -> (Client<Self, server::Server>, ClientHandle) {
         Client::new(self)
@@ -241,10 +487,28 @@ pub struct Client<T: Transport, S: server::ServerHandler> {
     >,
 
     // state
-    id_generator: IdGenerator,
+    id_generator: Box<dyn IdGenerator>,
     shutting_down: bool,
-    pending_client_requests: HashMap<Id, oneshot::Sender<Result<JsonValue>>>,
+    pending_client_requests: PendingCalls,
+    pending_batches: Vec<Vec<Id>>,
+    default_timeout: Option<Duration>,
+    deadlines: Deadlines,
+    cancel_notification: Option<CancelNotification>,
+    // ids of cancelled calls the server has not been notified about yet
+    cancelled_calls: VecDeque<Id>,
+    middleware: MiddlewareStack,
+    processing: Processing,
+    rate_limiter: Option<RateLimiter>,
+    protocol_version: ProtocolVersion,
+    #[cfg(feature = "metrics")]
+    metrics: Option<CallMetrics>,
     pending_payload: Option<String>,
+    // completions of the notifications in the pending payload
+    pending_notifications: Vec<NotificationCompletion>,
+    // completions of the notifications the transport has accepted, but not flushed yet
+    unflushed_notifications: Vec<NotificationCompletion>,
+    // calls the transport failed without a response from the server
+    call_failures_rx: mpsc::UnboundedReceiver<(Id, Error)>,
     fatal_error: Option<Error>,
 
     server_handler: S,
@@ -255,12 +519,39 @@ pub struct Client<T: Transport, S: server::ServerHandler> {
     transport_rx: T::Stream,
 }
 
+/// A message that has been passed through the middleware of a client.
+#[derive(Debug)]
+enum Processed {
+    Call(
+        Result<CallAction>,
+        oneshot::Sender<Result<JsonValue>>,
+        Option<Duration>,
+    ),
+    Notification(Result<Option<Notification>>, oneshot::Sender<Result<()>>),
+    Batch(Vec<Processed>),
+    Response(Id, Result<Output>),
+    BatchResponse(Vec<Processed>, Vec<Id>),
+}
+
+type ProcessingFuture = Box<dyn Future<Item = Processed, Error = ()> + Send>;
+
+type NotificationCompletion = oneshot::Sender<Result<()>>;
+
+/// The messages that are currently passing through the middleware of a client.
+struct Processing(Vec<ProcessingFuture>);
+
+impl fmt::Debug for Processing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Processing({} messages)", self.0.len())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 enum IncomingMessage {
     // take care, ordering here is important. Serde won't match a response struct if the request
     // comes first.
-    Response(Output),
+    Response(Response),
     Request(Request),
 }
 
@@ -285,7 +576,9 @@ impl<T: DuplexTransport, S: server::ServerHandler> Client<T, S> {
 }
 
 impl<T: Transport, S: server::ServerHandler> Client<T, S> {
-    fn new_with_server(transport: T, server_handler: S) -> (Self, ClientHandle) {
+    fn new_with_server(mut transport: T, server_handler: S) -> (Self, ClientHandle) {
+        let (call_failures, call_failures_rx) = CallFailures::new();
+        transport.set_call_failures(call_failures);
         let (transport_tx, transport_rx) = transport.io_pair();
         let (client_handle_tx, client_handle_rx) = mpsc::channel(0);
         let (server_response_tx, server_response_rx) = mpsc::channel(0);
@@ -299,11 +592,25 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
                 outgoing_payload_rx,
 
                 // state
-                id_generator: IdGenerator::new(),
+                id_generator: Box::new(NumericIdGenerator::new()),
                 pending_payload: None,
+                pending_notifications: Vec::new(),
+                unflushed_notifications: Vec::new(),
+                call_failures_rx,
                 shutting_down: false,
                 fatal_error: None,
-                pending_client_requests: HashMap::new(),
+                pending_client_requests: PendingCalls::new(),
+                pending_batches: Vec::new(),
+                default_timeout: None,
+                deadlines: Deadlines::new(),
+                cancel_notification: None,
+                cancelled_calls: VecDeque::new(),
+                middleware: MiddlewareStack::new(),
+                processing: Processing(Vec::new()),
+                rate_limiter: None,
+                protocol_version: ProtocolVersion::default(),
+                #[cfg(feature = "metrics")]
+                metrics: None,
 
                 // server handlers
                 server_handler,
@@ -313,10 +620,68 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
                 transport_tx,
                 transport_rx,
             },
-            ClientHandle { client_handle_tx },
+            ClientHandle {
+                client_handle_tx,
+                retry_policy: None,
+                circuit_breaker: None,
+            },
         )
     }
 
+    /// Sets the generator used for the ids of method calls. By default, calls get increasing
+    /// numeric ids.
+    pub fn id_generator(mut self, id_generator: impl IdGenerator + 'static) -> Self {
+        self.id_generator = Box::new(id_generator);
+        self
+    }
+
+    /// Sets the version of the JSON-RPC protocol spoken with the server. Defaults to
+    /// `ProtocolVersion::V2`.
+    pub fn protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.protocol_version = protocol_version;
+        self
+    }
+
+    /// Sets a default timeout for all method calls made through this client. Calls that are not
+    /// replied to in time fail with `ErrorKind::Timeout`. A timeout given to
+    /// `ClientHandle::call_method_with_timeout` takes precedence over this one.
+    ///
+    /// Timeouts are implemented with `tokio-timer`, so the client has to be driven on an executor
+    /// that provides a timer, such as a Tokio runtime or a `tokio-core` reactor.
+    pub fn default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the notification sent to the server when the future of a pending method call is
+    /// dropped. Regardless of this setting, the client stops waiting for the responses to dropped
+    /// calls.
+    pub fn cancel_notification(mut self, notification: CancelNotification) -> Self {
+        self.cancel_notification = Some(notification);
+        self
+    }
+
+    /// Adds a middleware layer to the client. Outgoing method calls and notifications pass through
+    /// the layers in the order they were added, responses pass through them in the reverse order.
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Limits the number of method calls in flight and the rate at which requests are sent. The
+    /// limit applies to the messages of all handles of this client together.
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(limit));
+        self
+    }
+
+    /// Sets the recorder receiving the metrics of the method calls made through this client.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_recorder(mut self, recorder: impl MetricsRecorder) -> Self {
+        self.metrics = Some(CallMetrics::new(recorder));
+        self
+    }
+
     fn should_shut_down(&mut self) -> bool {
         self.fatal_error.is_some() || self.shutting_down
     }
@@ -326,14 +691,25 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
     fn handle_messages(&mut self) -> Result<()> {
         // try send a leftover payload
         if let Some(payload) = self.pending_payload.take() {
-            self.send_payload(payload)?;
+            let notifications = mem::replace(&mut self.pending_notifications, Vec::new());
+            self.send_notification_payload(payload, notifications)?;
         }
         // drive server futures
         self.poll_server()?;
         // drain incoming payload
         self.poll_transport_rx()?;
+        // fail the calls the transport could not get a response to
+        self.poll_call_failures();
+        // handle messages that have passed through the middleware
+        self.poll_processing()?;
         // drain incoming rpc requests, only if the writing pipe is ready
         self.poll_outgoing_messages()?;
+        // forget about calls whose futures have been dropped
+        self.poll_cancelled_calls()?;
+        // time out calls that have passed their deadline
+        self.poll_deadlines();
+        // come back for rate limited messages if cancelled or timed out calls made room for them
+        self.notify_rate_limited();
         // poll transport tx to drive sending
         self.poll_transport_tx()?;
         Ok(())
@@ -351,6 +727,22 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
         }
     }
 
+    /// Sends a payload containing notifications. They are completed once the transport has
+    /// accepted and flushed the payload.
+    fn send_notification_payload(
+        &mut self,
+        payload: String,
+        notifications: Vec<NotificationCompletion>,
+    ) -> Result<()> {
+        self.send_payload(payload)?;
+        if self.pending_payload.is_some() {
+            self.pending_notifications.extend(notifications);
+        } else {
+            self.unflushed_notifications.extend(notifications);
+        }
+        Ok(())
+    }
+
     fn poll_transport_rx(&mut self) -> Result<()> {
         loop {
             match self
@@ -372,20 +764,122 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
     }
 
     fn handle_transport_rx_payload(&mut self, payload: &str) -> Result<()> {
-        let msg: IncomingMessage =
-            serde_json::from_str(&payload).chain_err(|| ErrorKind::DeserializeError)?;
+        let msg: IncomingMessage = self
+            .protocol_version
+            .deserialize(payload)
+            .chain_err(|| ErrorKind::DeserializeError)?;
         match msg {
             IncomingMessage::Request(req) => self
                 .server_handler
                 .process_request(req, self.server_response_tx.clone()),
-            IncomingMessage::Response(response) => self.handle_response(response),
+            IncomingMessage::Response(Response::Single(output)) => self.handle_response(output),
+            IncomingMessage::Response(Response::Batch(outputs)) => {
+                self.handle_batch_response(outputs)
+            }
         }
     }
 
+    fn handle_batch_response(&mut self, outputs: Vec<Output>) -> Result<()> {
+        // A batch response contains the responses to all calls of a single batch request. The
+        // server answers entries it could not make sense of with an error without an id, and if
+        // only such errors were returned, they are assumed to belong to the oldest batch.
+        let answered_ids: HashSet<Id> = outputs.iter().map(|output| output.id().clone()).collect();
+        let index = match self
+            .pending_batches
+            .iter()
+            .position(|ids| ids.iter().any(|id| answered_ids.contains(id)))
+        {
+            None if answered_ids.contains(&Id::Null) && !self.pending_batches.is_empty() => Some(0),
+            index => index,
+        };
+        let batch_ids = index.map_or_else(Vec::new, |index| self.pending_batches.remove(index));
+
+        // The responses of the batch are handled together once all of them have passed through
+        // the middleware, so that the calls left unanswered can be told apart.
+        let mut responses: Vec<ProcessingFuture> = Vec::with_capacity(outputs.len());
+        for output in outputs {
+            if self.protocol_version.accepts(output.version()) {
+                responses.push(self.process_response(output));
+            } else {
+                warn!("Response to call {:?} has an invalid version", output.id());
+                let response =
+                    Processed::Response(output.id().clone(), Err(ErrorKind::InvalidVersion.into()));
+                responses.push(Box::new(future::ok(response)));
+            }
+        }
+        let processing = future::join_all(responses)
+            .map(move |responses| Processed::BatchResponse(responses, batch_ids));
+        self.start_processing(Box::new(processing))?;
+
+        // Forget about batches which have been fully answered through other means.
+        let pending_client_requests = &self.pending_client_requests;
+        self.pending_batches.retain(|ids| {
+            ids.iter()
+                .any(|id| pending_client_requests.contains_key(id))
+        });
+        Ok(())
+    }
+
     fn handle_response(&mut self, output: Output) -> Result<()> {
-        if output.version() != Some(jsonrpc_core::types::Version::V2) {
+        if !self.protocol_version.accepts(output.version()) {
             return Err(ErrorKind::InvalidVersion.into());
         };
+        let processing = self.process_response(output);
+        self.start_processing(processing)
+    }
+
+    /// Passes a response from the server through the middleware.
+    fn process_response(&self, output: Output) -> ProcessingFuture {
+        let id = output.id().clone();
+        Box::new(
+            self.middleware
+                .response(output)
+                .then(move |result| Ok(Processed::Response(id, result))),
+        )
+    }
+
+    /// Completes the calls of a batch with their processed responses. Calls of the batch that
+    /// were not answered will never get a response.
+    fn handle_processed_batch_response(
+        &mut self,
+        responses: Vec<Processed>,
+        batch_ids: Vec<Id>,
+    ) -> Result<()> {
+        let mut batch_error = None;
+        for response in responses {
+            match response {
+                Processed::Response(
+                    _,
+                    Ok(Output::Failure(RpcFailure {
+                        id: Id::Null,
+                        error,
+                        ..
+                    })),
+                ) => {
+                    debug!(
+                        "Batch response contains an error without an id: {:?}",
+                        error
+                    );
+                    batch_error = Some(error);
+                }
+                response => self.handle_processed(response)?,
+            }
+        }
+
+        for id in batch_ids {
+            if self.pending_client_requests.contains_key(&id) {
+                debug!("No response to call {:?} in batch response", id);
+                let error = match batch_error {
+                    Some(ref error) => ErrorKind::JsonRpcError(error.clone()),
+                    None => ErrorKind::MissingBatchResponse,
+                };
+                self.resolve_call(&id, Err(error.into()));
+            }
+        }
+        Ok(())
+    }
+
+    fn complete_call(&mut self, output: Output) {
         let (id, result): (Id, Result<JsonValue>) = match output {
             Output::Success(RpcSuccess { result, id, .. }) => (id, Ok(result)),
             Output::Failure(RpcFailure { id, error, .. }) => {
@@ -393,21 +887,54 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
             }
         };
 
-        match self.pending_client_requests.remove(&id) {
-            Some(completion_chan) => Self::send_rpc_response(&id, completion_chan, result),
-            None => trace!("Received response with an invalid id {:?}", id),
-        };
-        Ok(())
+        if !self.resolve_call(&id, result) {
+            trace!("Received response with an invalid id {:?}", id);
+            self.record_unknown_response(&id);
+        }
+    }
+
+    /// Completes the pending call with the given id. Returns false if there is no such call.
+    fn resolve_call(&mut self, id: &Id, result: Result<JsonValue>) -> bool {
+        match self.pending_client_requests.remove(id) {
+            Some(completion_chan) => {
+                self.record_call_completed(id, &result);
+                Self::send_rpc_response(id, completion_chan, result);
+                true
+            }
+            None => false,
+        }
     }
 
     fn poll_outgoing_messages(&mut self) -> Result<()> {
         // Process new client payloads if the transport is ready to send new ones
         while self.pending_payload.is_none() {
-            // There's no pending payload, so new RPC requests can be processed.
-            match self.outgoing_payload_rx.poll() {
+            // Messages held back by the rate limit go before any new ones.
+            if let Some(message) = self.poll_rate_limited() {
+                self.handle_client_payload(message)?;
+                continue;
+            }
+            // While messages are held back by the rate limit, new messages from the handles are
+            // left in their channel to wait as well. Responses to the server are never held back.
+            let held_back = match self.rate_limiter {
+                Some(ref rate_limiter) => rate_limiter.is_holding(),
+                None => false,
+            };
+            let message = if held_back {
+                self.outgoing_payload_rx.poll_weak()
+            } else {
+                // There's no pending payload, so new RPC requests can be processed.
+                self.outgoing_payload_rx.poll()
+            };
+            match message {
                 Ok(Async::NotReady) => return Ok(()),
                 Ok(Async::Ready(Some(call))) => {
-                    self.handle_client_payload(call)?;
+                    let call = match self.rate_limiter {
+                        Some(ref mut rate_limiter) => rate_limiter.hold(call),
+                        None => Some(call),
+                    };
+                    if let Some(call) = call {
+                        self.handle_client_payload(call)?;
+                    }
                 }
                 Ok(Async::Ready(None)) => {
                     trace!("All client handles and futures dropped, shutting down");
@@ -421,28 +948,157 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
         Ok(())
     }
 
+    /// Returns the next message held back by the rate limit, if it may be sent now.
+    fn poll_rate_limited(&mut self) -> Option<OutgoingMessage> {
+        let in_flight = self.calls_in_flight();
+        let rate_limiter = self.rate_limiter.as_mut()?;
+        match rate_limiter.poll_next(in_flight) {
+            Ok(message) => message,
+            Err(e) => {
+                error!("Unable to wait for the rate limit, sending anyway: {}", e);
+                rate_limiter.pop()
+            }
+        }
+    }
+
+    /// Responses wake the client up on their own, but calls that are cancelled or time out after
+    /// the rate limited messages have been polled need another round to make room for them.
+    fn notify_rate_limited(&self) {
+        let in_flight = self.calls_in_flight();
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            if self.pending_payload.is_none() && rate_limiter.has_room(in_flight) {
+                futures::task::current().notify();
+            }
+        }
+    }
+
+    /// Returns the number of method calls waiting for a response. Messages still passing through
+    /// the middleware count as in flight as well.
+    fn calls_in_flight(&self) -> usize {
+        self.pending_client_requests.len() + self.processing.0.len()
+    }
+
     fn handle_client_payload(&mut self, message: OutgoingMessage) -> Result<()> {
         match message {
-            OutgoingMessage::RpcCall(method, parameters, completion) => {
-                let new_id = self.id_generator.next();
-                match serialize_method_request(new_id.clone(), method, &parameters) {
+            OutgoingMessage::Response(response) => {
+                let payload = self.protocol_version.serialize(&response);
+                self.send_payload(payload.chain_err(|| ErrorKind::SerializeError)?)
+            }
+            message => {
+                let processing = self.process_outgoing(message);
+                self.start_processing(processing)
+            }
+        }
+    }
+
+    /// Passes an outgoing method call, notification or batch through the middleware.
+    fn process_outgoing(&mut self, message: OutgoingMessage) -> ProcessingFuture {
+        match message {
+            OutgoingMessage::RpcCall(method, params, completion, timeout) => {
+                let method_call = MethodCall {
+                    jsonrpc: self.protocol_version.version(),
+                    method,
+                    params,
+                    id: self.id_generator.next_id(),
+                };
+                Box::new(
+                    self.middleware
+                        .method_call(method_call)
+                        .then(move |result| Ok(Processed::Call(result, completion, timeout))),
+                )
+            }
+            OutgoingMessage::Notification(method, params, completion) => {
+                let notification = Notification {
+                    jsonrpc: self.protocol_version.version(),
+                    method,
+                    params,
+                };
+                Box::new(
+                    self.middleware
+                        .notification(notification)
+                        .then(move |result| Ok(Processed::Notification(result, completion))),
+                )
+            }
+            OutgoingMessage::Batch(entries) => {
+                let mut processing_entries = Vec::with_capacity(entries.len());
+                for entry in entries {
+                    match entry {
+                        OutgoingMessage::Batch(_) | OutgoingMessage::Response(_) => error!(
+                            "Dropping message that can't be part of a batch: {:?}",
+                            entry
+                        ),
+                        entry => processing_entries.push(self.process_outgoing(entry)),
+                    }
+                }
+                Box::new(future::join_all(processing_entries).map(Processed::Batch))
+            }
+            OutgoingMessage::Response(_) => {
+                unreachable!("Responses are never passed through the middleware")
+            }
+        }
+    }
+
+    /// Polls a message that is being processed by the middleware once, so that it is either
+    /// handled right away or the current task is notified once it is ready.
+    fn start_processing(&mut self, mut processing: ProcessingFuture) -> Result<()> {
+        match processing.poll() {
+            Ok(Async::Ready(processed)) => self.handle_processed(processed),
+            Ok(Async::NotReady) => {
+                self.processing.0.push(processing);
+                Ok(())
+            }
+            Err(()) => unreachable!("Middleware processing can't fail"),
+        }
+    }
+
+    fn poll_processing(&mut self) -> Result<()> {
+        let mut index = 0;
+        // Handling a processed message might produce a new payload, so only proceed when the
+        // transport is ready to send it.
+        while index < self.processing.0.len() && self.pending_payload.is_none() {
+            match self.processing.0[index].poll() {
+                Ok(Async::Ready(processed)) => {
+                    let _ = self.processing.0.remove(index);
+                    self.handle_processed(processed)?;
+                }
+                Ok(Async::NotReady) => index += 1,
+                Err(()) => unreachable!("Middleware processing can't fail"),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_processed(&mut self, processed: Processed) -> Result<()> {
+        match processed {
+            Processed::Call(Ok(CallAction::Send(method_call)), completion, timeout) => {
+                let payload = self.protocol_version.serialize(&method_call);
+                match payload.chain_err(|| ErrorKind::SerializeError) {
                     Ok(payload) => {
-                        self.add_new_call(new_id, completion);
+                        self.add_new_call(method_call.id, &method_call.method, completion, timeout);
                         self.send_payload(payload)?;
                     }
-                    Err(e) => {
-                        Self::send_rpc_response(&new_id, completion, Err(e));
+                    Err(e) => Self::send_rpc_response(&method_call.id, completion, Err(e)),
+                }
+            }
+            Processed::Call(Ok(CallAction::Respond(output)), completion, _) => {
+                let id = output.id().clone();
+                let result = match output {
+                    Output::Success(RpcSuccess { result, .. }) => Ok(result),
+                    Output::Failure(RpcFailure { error, .. }) => {
+                        Err(ErrorKind::JsonRpcError(error).into())
                     }
                 };
+                Self::send_rpc_response(&id, completion, result);
             }
-            OutgoingMessage::Notification(method, parameters, completion) => {
-                match serialize_notification_request(method, &parameters) {
-                    Ok(payload) => {
-                        if completion.send(Ok(())).is_err() {
-                            trace!("future for notification dopped already");
-                        }
-                        self.send_payload(payload)?;
-                    }
+            Processed::Call(Err(e), completion, _) => {
+                if completion.send(Err(e)).is_err() {
+                    trace!("Future for rejected RPC call already dropped");
+                }
+            }
+            Processed::Notification(Ok(Some(notification)), completion) => {
+                let payload = self.protocol_version.serialize(&notification);
+                match payload.chain_err(|| ErrorKind::SerializeError) {
+                    Ok(payload) => self.send_notification_payload(payload, vec![completion])?,
                     Err(e) => {
                         if completion.send(Err(e)).is_err() {
                             trace!("Future for notification already dropped");
@@ -450,10 +1106,77 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
                     }
                 }
             }
-            OutgoingMessage::Response(response) => {
-                self.send_payload(
-                    serde_json::to_string(&response).chain_err(|| ErrorKind::SerializeError)?,
-                )?;
+            Processed::Notification(result, completion) => {
+                if completion.send(result.map(|_| ())).is_err() {
+                    trace!("Future for notification already dropped");
+                }
+            }
+            Processed::Batch(entries) => self.handle_processed_batch(entries)?,
+            Processed::Response(_, Ok(output)) => self.complete_call(output),
+            Processed::Response(id, Err(e)) => {
+                self.resolve_call(&id, Err(e));
+            }
+            Processed::BatchResponse(responses, batch_ids) => {
+                self.handle_processed_batch_response(responses, batch_ids)?
+            }
+        };
+        Ok(())
+    }
+
+    fn handle_processed_batch(&mut self, entries: Vec<Processed>) -> Result<()> {
+        let mut calls = Vec::with_capacity(entries.len());
+        let mut batch_ids = Vec::new();
+        let mut method_completions = Vec::new();
+        let mut notification_completions = Vec::new();
+
+        for entry in entries {
+            match entry {
+                Processed::Call(Ok(CallAction::Send(method_call)), completion, timeout) => {
+                    batch_ids.push(method_call.id.clone());
+                    method_completions.push((
+                        method_call.id.clone(),
+                        method_call.method.clone(),
+                        completion,
+                        timeout,
+                    ));
+                    calls.push(Call::MethodCall(method_call));
+                }
+                Processed::Notification(Ok(Some(notification)), completion) => {
+                    calls.push(Call::Notification(notification));
+                    notification_completions.push(completion);
+                }
+                // Entries which have been answered or rejected by the middleware can be completed
+                // right away.
+                entry => self.handle_processed(entry)?,
+            }
+        }
+
+        if calls.is_empty() {
+            return Ok(());
+        }
+
+        match self.protocol_version.serialize(&Request::Batch(calls)) {
+            Ok(payload) => {
+                if !batch_ids.is_empty() {
+                    self.pending_batches.push(batch_ids);
+                }
+                for (id, method, completion, timeout) in method_completions {
+                    self.add_new_call(id, &method, completion, timeout);
+                }
+                self.send_notification_payload(payload, notification_completions)?;
+            }
+            Err(_) => {
+                for (id, _, completion, _) in method_completions {
+                    Self::send_rpc_response(&id, completion, Err(ErrorKind::SerializeError.into()));
+                }
+                for completion in notification_completions {
+                    if completion
+                        .send(Err(ErrorKind::SerializeError.into()))
+                        .is_err()
+                    {
+                        trace!("Future for batched notification already dropped");
+                    }
+                }
             }
         };
         Ok(())
@@ -482,6 +1205,7 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
                 e.description()
             );
         }
+        self.poll_call_failures();
         match self
             .transport_tx
             .close()
@@ -496,7 +1220,7 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
                     e.description()
                 );
             }
-            _ => (),
+            Ok(Async::Ready(())) => self.complete_flushed_notifications(),
         }
 
         self.fatal_error
@@ -505,18 +1229,143 @@ impl<T: Transport, S: server::ServerHandler> Client<T, S> {
             .unwrap_or(Ok(Async::Ready(())))
     }
 
-    fn add_new_call(&mut self, id: Id, completion: oneshot::Sender<Result<JsonValue>>) {
-        self.pending_client_requests.insert(id, completion);
+    fn add_new_call(
+        &mut self,
+        id: Id,
+        method: &str,
+        completion: oneshot::Sender<Result<JsonValue>>,
+        timeout: Option<Duration>,
+    ) {
+        if let Some(timeout) = timeout.or(self.default_timeout) {
+            self.deadlines.insert(id.clone(), Instant::now() + timeout);
+        }
+        self.pending_client_requests.insert(id.clone(), completion);
+        self.record_call_sent(&id, method);
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_call_sent(&mut self, id: &Id, method: &str) {
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.call_sent(id, method, self.pending_client_requests.len());
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_call_completed(&mut self, id: &Id, result: &Result<JsonValue>) {
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.call_completed(id, result, self.pending_client_requests.len());
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_call_cancelled(&mut self, id: &Id) {
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.call_cancelled(id, self.pending_client_requests.len());
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_unknown_response(&mut self, id: &Id) {
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.unknown_response(id);
+        }
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    fn record_call_sent(&mut self, _id: &Id, _method: &str) {}
+
+    #[cfg(not(feature = "metrics"))]
+    fn record_call_completed(&mut self, _id: &Id, _result: &Result<JsonValue>) {}
+
+    #[cfg(not(feature = "metrics"))]
+    fn record_call_cancelled(&mut self, _id: &Id) {}
+
+    #[cfg(not(feature = "metrics"))]
+    fn record_unknown_response(&mut self, _id: &Id) {}
+
+    fn poll_cancelled_calls(&mut self) -> Result<()> {
+        for id in self.pending_client_requests.poll_cancelled() {
+            trace!("Future for RPC call {:?} dropped, cancelling the call", id);
+            self.record_call_cancelled(&id);
+            if self.cancel_notification.is_some() {
+                self.cancelled_calls.push_back(id);
+            }
+        }
+
+        // Cancel notifications are sent one at a time, whenever the transport is ready to send a
+        // new payload.
+        while self.pending_payload.is_none() {
+            let (method, params) = match (
+                self.cancelled_calls.pop_front(),
+                self.cancel_notification.as_ref(),
+            ) {
+                (Some(id), Some(cancel_notification)) => cancel_notification.build(&id),
+                _ => return Ok(()),
+            };
+            let notification = Notification {
+                jsonrpc: self.protocol_version.version(),
+                method,
+                params,
+            };
+            let payload = self.protocol_version.serialize(&notification);
+            self.send_payload(payload.chain_err(|| ErrorKind::SerializeError)?)?;
+        }
+        Ok(())
+    }
+
+    fn poll_call_failures(&mut self) {
+        let mut failed = false;
+        while let Ok(Async::Ready(Some((id, error)))) = self.call_failures_rx.poll() {
+            if self.resolve_call(&id, Err(error)) {
+                debug!("Call {:?} failed in the transport", id);
+                failed = true;
+            }
+        }
+        // Forget about batches whose calls have all failed.
+        if failed {
+            let pending_client_requests = &self.pending_client_requests;
+            self.pending_batches.retain(|ids| {
+                ids.iter()
+                    .any(|id| pending_client_requests.contains_key(id))
+            });
+        }
+    }
+
+    fn poll_deadlines(&mut self) {
+        let expired_ids = match self.deadlines.poll_expired() {
+            Ok(ids) => ids,
+            Err(e) => {
+                error!("Unable to track call deadlines, failing calls: {}", e);
+                self.deadlines.drain()
+            }
+        };
+        for id in expired_ids {
+            if self.resolve_call(&id, Err(ErrorKind::Timeout.into())) {
+                debug!("Call {:?} timed out", id);
+            }
+        }
     }
 
     fn poll_transport_tx(&mut self) -> Result<()> {
         if self.fatal_error.is_none() {
-            self.transport_tx
+            let flushed = self
+                .transport_tx
                 .poll_complete()
                 .chain_err(|| ErrorKind::TransportError)?;
+            if flushed.is_ready() {
+                self.complete_flushed_notifications();
+            }
         }
         Ok(())
     }
+
+    fn complete_flushed_notifications(&mut self) {
+        for completion in self.unflushed_notifications.drain(..) {
+            if completion.send(Ok(())).is_err() {
+                trace!("Future for notification dropped already");
+            }
+        }
+    }
 }
 
 impl<T: Transport, S: server::ServerHandler> Future for Client<T, S> {
@@ -541,28 +1390,29 @@ impl<T: Transport, S: server::ServerHandler> Future for Client<T, S> {
 /// server. This can be a request, a notification or a response to a previously received request.
 #[derive(Debug)]
 pub enum OutgoingMessage {
-    /// Invoke an RPC
-    RpcCall(String, Option<Params>, oneshot::Sender<Result<JsonValue>>),
+    /// Invoke an RPC, optionally with a timeout that overrides the default timeout of the client
+    RpcCall(
+        String,
+        Option<Params>,
+        oneshot::Sender<Result<JsonValue>>,
+        Option<Duration>,
+    ),
     /// Send a notification
     Notification(String, Option<Params>, oneshot::Sender<Result<()>>),
     /// Send a response response
     Response(Response),
+    /// Send several method calls and notifications as a single batch request. Only `RpcCall` and
+    /// `Notification` messages can be part of a batch.
+    Batch(Vec<OutgoingMessage>),
 }
 
-/// Creates a JSON-RPC 2.0 request to the given method with the given parameters.
-fn serialize_method_request(
-    id: Id,
-    method: String,
-    params: &impl serde::Serialize,
-) -> Result<String> {
-    let serialized_params = serialize_parameters(params)?;
-    let method_call = MethodCall {
-        jsonrpc: Some(Version::V2),
-        method,
-        params: serialized_params,
-        id,
-    };
-    serde_json::to_string(&method_call).chain_err(|| ErrorKind::SerializeError)
+/// Waits for the result of an RPC call and deserializes it into the desired type.
+fn await_rpc_response<T: serde::de::DeserializeOwned>(
+    rx: oneshot::Receiver<Result<JsonValue>>,
+) -> impl Future<Item = T, Error = Error> {
+    rx.map_err(|_| ErrorKind::Shutdown)
+        .flatten()
+        .and_then(|r| serde_json::from_value(r).chain_err(|| ErrorKind::DeserializeError))
 }
 
 /// Serializes parameters for JSON-RPC 2.0 methods and notifications
@@ -576,16 +1426,43 @@ pub fn serialize_parameters(params: &impl serde::Serialize) -> Result<Option<Par
     Ok(parameters)
 }
 
-/// Creates a JSON-RPC 2.0 notification request to the given method with the given parameters.
-fn serialize_notification_request(
-    method: String,
-    params: &impl serde::Serialize,
-) -> Result<String> {
-    let serialized_params = serialize_parameters(params)?;
-    let notification = Notification {
-        jsonrpc: Some(Version::V2),
-        method,
-        params: serialized_params,
-    };
-    serde_json::to_string(&notification).chain_err(|| ErrorKind::SerializeError)
+/// Serializes a single argument of a method generated by `jsonrpc_client!`.
+#[doc(hidden)]
+pub fn serialize_parameter(param: &impl serde::Serialize) -> Result<JsonValue> {
+    serde_json::to_value(param).chain_err(|| ErrorKind::SerializeError)
+}
+
+/// Collects serialized arguments into positional parameters. If `omit_trailing_nulls` is set,
+/// trailing arguments that serialized to `null`, such as optional arguments set to `None`, are
+/// left out.
+#[doc(hidden)]
+pub fn positional_parameters(
+    params: Vec<Result<JsonValue>>,
+    omit_trailing_nulls: bool,
+) -> Result<Option<Params>> {
+    let mut params = params.into_iter().collect::<Result<Vec<_>>>()?;
+    if omit_trailing_nulls {
+        while params.last().map_or(false, JsonValue::is_null) {
+            params.pop();
+        }
+    }
+    Ok(Some(Params::Array(params)))
+}
+
+/// Collects serialized arguments into parameters passed by name. Trailing arguments that
+/// serialized to `null`, such as optional arguments set to `None`, are left out.
+#[doc(hidden)]
+pub fn named_parameters(params: Vec<(&str, Result<JsonValue>)>) -> Result<Option<Params>> {
+    let mut params = params
+        .into_iter()
+        .map(|(name, param)| param.map(|param| (name, param)))
+        .collect::<Result<Vec<_>>>()?;
+    while params.last().map_or(false, |(_, param)| param.is_null()) {
+        params.pop();
+    }
+    let map = params
+        .into_iter()
+        .map(|(name, param)| (name.to_owned(), param))
+        .collect();
+    Ok(Some(Params::Map(map)))
 }
@@ -8,18 +8,226 @@
 
 /// The main macro of this crate. Generates JSON-RPC 2.0 client structs with automatic serialization
 /// and deserialization. Method calls get correct types automatically.
+///
+/// The struct can optionally start with an application error enum. Each variant is bound to a
+/// server defined error code, and all methods of the client then fail with this enum instead of
+/// `Error`. JSON-RPC 2.0 errors with a listed code map to the corresponding variant, holding the
+/// error object, and all other errors map to the `Other` variant.
+///
+/// Methods that are safe to call more than once can be marked with `#[idempotent]`. Failed calls
+/// to them are retried according to the `RetryPolicy` of the `ClientHandle` the client was created
+/// with.
+///
+/// Arguments are sent as an array by default. Methods marked with `#[named_params]` send them as
+/// an object keyed by the argument names instead, and `#[named_params(arg = "name")]` sends an
+/// argument under another name. Renaming an argument the method does not have is a compile error.
+/// Marking the struct with `#[named_params]` does so for all of its methods. Trailing arguments
+/// serializing to `null`, such as optional arguments set to `None`, are left out of objects.
+/// Methods marked with `#[omit_trailing_none]`, or all methods of a struct marked with it, leave
+/// them out of arrays as well.
+///
+/// The RPC method called is named after the Rust method. `#[rpc(name = "...")]` calls a method
+/// with another name, for names that are not valid identifiers. Marking the struct with
+/// `#[rpc(namespace = "...")]` prefixes the names of all other methods with the namespace and a
+/// dot.
+///
+/// The struct and its methods can have any visibility, including `pub(in path)`. Methods can take
+/// `&self` or `&mut self`, as calls don't need exclusive access to the client, and can be generic.
+/// Generic parameters and `where` clauses take lifetimes and trait paths with generic arguments as
+/// bounds, joined with `+`. `?Sized` and bounds on `Fn` traits are not supported.
+///
+/// Notifications are declared with `pub notify fn` and without a return type. Their methods send
+/// the notification and return a future resolving once the transport has accepted and flushed it.
+///
+/// The methods return an [`RpcFuture`](struct.RpcFuture.html), which can also be awaited in async
+/// code when the `std-future` feature is enabled.
+///
+/// ```rust,ignore
+/// jsonrpc_client!(pub struct AccountClient {
+///     pub enum AccountError {
+///         /// The account does not exist.
+///         NoSuchAccount = -32001,
+///         /// The account is locked by another session.
+///         Locked = -32002,
+///     }
+///
+///     #[idempotent]
+///     #[omit_trailing_none]
+///     pub fn balance(&self, account: &str, block: Option<u64>) -> Future<u64>;
+///
+///     pub(crate) fn setting<T>(&self, key: &str) -> Future<T> where T: DeserializeOwned + Send;
+///
+///     #[rpc(name = "system.listMethods")]
+///     pub fn list_methods(&mut self) -> Future<Vec<String>>;
+///
+///     #[named_params(account = "accountId")]
+///     pub fn transfer(&mut self, account: &str, amount: u64, memo: Option<String>) -> Future<()>;
+///
+///     pub notify fn log_event(&mut self, event: &str);
+/// });
+/// ```
 #[macro_export]
 macro_rules! jsonrpc_client {
     (
-        $(#[$struct_attr:meta])*
-        pub struct $struct_name:ident {$(
-            $(#[$attr:meta])*
-            pub fn $method:ident(&mut $selff:ident $(, $arg_name:ident: $arg_ty:ty)*)
-                -> Future<$return_ty:ty>;
+        $(#[$($struct_attr:tt)*])*
+        $(pub $(($($restrict:tt)+))*)* struct $struct_name:ident {
+            $(#[$error_attr:meta])*
+            pub enum $error_name:ident {$(
+                $(#[$variant_attr:meta])*
+                $variant:ident = $code:expr,
+            )*}
+            $(
+                $(#[$($attr:tt)*])*
+                $($head:ident)+ $(($vis:ident $($vis_path:tt)*) $($head2:ident)+)*
+                    $(<$($generic:tt $(:
+                        $bound:tt $(:: $bound_seg:ident)* $(<$($bound_arg:ty),*>)*
+                        $(+ $more_bound:tt $(:: $more_seg:ident)* $(<$($more_arg:ty),*>)*)*
+                    )*),*>)*
+                    (&$($receiver:ident)+ $(, $arg_name:ident: $arg_ty:ty)*)
+                    $(-> Future<$return_ty:ty>)*
+                    $(where $($where_ty:ty:
+                        $where_bound:tt $(:: $where_seg:ident)* $(<$($where_arg:ty),*>)*
+                        $(+ $where_more:tt $(:: $where_more_seg:ident)*
+                            $(<$($where_more_arg:ty),*>)*)*
+                    ),+)*;
+            )*
+        }
+    ) => (
+        jsonrpc_client_error!(
+            $(#[$error_attr])*
+            pub enum $error_name {$(
+                $(#[$variant_attr])*
+                $variant = $code,
+            )*}
+        );
+
+        jsonrpc_client_struct!(
+            $(#[$($struct_attr)*])*
+            $(pub $(($($restrict)+))*)* struct $struct_name: $error_name {$(
+                $(#[$($attr)*])*
+                $($head)+ $(($vis $($vis_path)*) $($head2)+)*
+                    $(<$($generic $(:
+                        $bound $(:: $bound_seg)* $(<$($bound_arg),*>)*
+                        $(+ $more_bound $(:: $more_seg)* $(<$($more_arg),*>)*)*
+                    )*),*>)*
+                    (&$($receiver)+ $(, $arg_name: $arg_ty)*)
+                    $(-> Future<$return_ty>)*
+                    $(where $($where_ty:
+                        $where_bound $(:: $where_seg)* $(<$($where_arg),*>)*
+                        $(+ $where_more $(:: $where_more_seg)* $(<$($where_more_arg),*>)*)*
+                    ),+)*;
+            )*}
+        );
+    );
+    (
+        $(#[$($struct_attr:tt)*])*
+        $(pub $(($($restrict:tt)+))*)* struct $struct_name:ident {$(
+            $(#[$($attr:tt)*])*
+            $($head:ident)+ $(($vis:ident $($vis_path:tt)*) $($head2:ident)+)*
+                $(<$($generic:tt $(:
+                    $bound:tt $(:: $bound_seg:ident)* $(<$($bound_arg:ty),*>)*
+                    $(+ $more_bound:tt $(:: $more_seg:ident)* $(<$($more_arg:ty),*>)*)*
+                )*),*>)*
+                (&$($receiver:ident)+ $(, $arg_name:ident: $arg_ty:ty)*)
+                $(-> Future<$return_ty:ty>)*
+                $(where $($where_ty:ty:
+                    $where_bound:tt $(:: $where_seg:ident)* $(<$($where_arg:ty),*>)*
+                    $(+ $where_more:tt $(:: $where_more_seg:ident)* $(<$($where_more_arg:ty),*>)*)*
+                ),+)*;
+        )*}
+    ) => (
+        jsonrpc_client_struct!(
+            $(#[$($struct_attr)*])*
+            $(pub $(($($restrict)+))*)* struct $struct_name: $crate::Error {$(
+                $(#[$($attr)*])*
+                $($head)+ $(($vis $($vis_path)*) $($head2)+)*
+                    $(<$($generic $(:
+                        $bound $(:: $bound_seg)* $(<$($bound_arg),*>)*
+                        $(+ $more_bound $(:: $more_seg)* $(<$($more_arg),*>)*)*
+                    )*),*>)*
+                    (&$($receiver)+ $(, $arg_name: $arg_ty)*)
+                    $(-> Future<$return_ty>)*
+                    $(where $($where_ty:
+                        $where_bound $(:: $where_seg)* $(<$($where_arg),*>)*
+                        $(+ $where_more $(:: $where_more_seg)* $(<$($where_more_arg),*>)*)*
+                    ),+)*;
+            )*}
+        );
+    );
+}
+
+
+/// Generates the client struct for `jsonrpc_client`, with all methods failing with the given error
+/// type. Collects the attributes of the struct one at a time, taking `#[named_params]` and
+/// `#[omit_trailing_none]` out to make them the default of all methods and
+/// `#[rpc(namespace = "...")]` out to prefix all method names.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! jsonrpc_client_struct {
+    (
+        $(#[$($struct_attr:tt)*])*
+        $(pub $(($($restrict:tt)+))*)* struct $struct_name:ident: $error_ty:ty {$($methods:tt)*}
+    ) => (
+        jsonrpc_client_struct!(
+            [] [positional] []
+            $(#[$($struct_attr)*])*
+            $(pub $(($($restrict)+))*)* struct $struct_name: $error_ty {$($methods)*}
+        );
+    );
+    (
+        [$($struct_attrs:tt)*] $params:tt $name:tt
+        #[named_params]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_struct!([$($struct_attrs)*] [named ()] $name $($rest)*);
+    );
+    (
+        [$($struct_attrs:tt)*] [positional] $name:tt
+        #[omit_trailing_none]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_struct!([$($struct_attrs)*] [positional omit] $name $($rest)*);
+    );
+    (
+        [$($struct_attrs:tt)*] $params:tt $name:tt
+        #[omit_trailing_none]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_struct!([$($struct_attrs)*] $params $name $($rest)*);
+    );
+    (
+        [$($struct_attrs:tt)*] $params:tt $name:tt
+        #[rpc(namespace = $namespace:expr)]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_struct!([$($struct_attrs)*] $params [namespace $namespace] $($rest)*);
+    );
+    (
+        [$($struct_attrs:tt)*] $params:tt $name:tt
+        #[$($struct_attr:tt)*]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_struct!([$($struct_attrs)* #[$($struct_attr)*]] $params $name $($rest)*);
+    );
+    (
+        [$($struct_attrs:tt)*] $params:tt $name:tt
+        $(pub $(($($restrict:tt)+))*)* struct $struct_name:ident: $error_ty:ty {$(
+            $(#[$($attr:tt)*])*
+            $($head:ident)+ $(($vis:ident $($vis_path:tt)*) $($head2:ident)+)*
+                $(<$($generic:tt $(:
+                    $bound:tt $(:: $bound_seg:ident)* $(<$($bound_arg:ty),*>)*
+                    $(+ $more_bound:tt $(:: $more_seg:ident)* $(<$($more_arg:ty),*>)*)*
+                )*),*>)*
+                (&$($receiver:ident)+ $(, $arg_name:ident: $arg_ty:ty)*)
+                $(-> Future<$return_ty:ty>)*
+                $(where $($where_ty:ty:
+                    $where_bound:tt $(:: $where_seg:ident)* $(<$($where_arg:ty),*>)*
+                    $(+ $where_more:tt $(:: $where_more_seg:ident)* $(<$($where_more_arg:ty),*>)*)*
+                ),+)*;
         )*}
     ) => (
-        $(#[$struct_attr])*
-        pub struct $struct_name {
+        $($struct_attrs)*
+        $(pub $(($($restrict)+))*)* struct $struct_name {
             client: $crate::ClientHandle,
         }
 
@@ -30,29 +238,495 @@ macro_rules! jsonrpc_client {
             }
 
             $(
-                $(#[$attr])*
-                pub fn $method(&mut $selff $(, $arg_name: $arg_ty)*)
-                    -> impl $crate::Future<Item = $return_ty, Error = $crate::Error> + 'static
-                {
-                    let method = String::from(stringify!($method));
-                    let raw_params = expand_params!($($arg_name,)*);
-                    let params = $crate::serialize_parameters(&raw_params);
-                    let (tx, rx) = $crate::oneshot::channel();
-                    let client_call = params.map(|p| $crate::OutgoingMessage::RpcCall(method, p, tx));
-                    $selff.client.send_client_call(client_call, rx)
-                }
+                jsonrpc_client_method!(
+                    [] [false] [$error_ty] $params $name
+                    $(#[$($attr)*])*
+                    $($head)+ $(($vis $($vis_path)*) $($head2)+)*
+                        $(<$($generic $(:
+                            $bound $(:: $bound_seg)* $(<$($bound_arg),*>)*
+                            $(+ $more_bound $(:: $more_seg)* $(<$($more_arg),*>)*)*
+                        )*),*>)*
+                        (&$($receiver)+ $(, $arg_name: $arg_ty)*)
+                        $(-> Future<$return_ty>)*
+                        $(where $($where_ty:
+                            $where_bound $(:: $where_seg)* $(<$($where_arg),*>)*
+                            $(+ $where_more $(:: $where_more_seg)* $(<$($where_more_arg),*>)*)*
+                        ),+)*;
+                );
+            )*
+        }
+    );
+}
+
+
+/// Generates a single method for `jsonrpc_client_struct`. Collects the attributes of the method
+/// one at a time, taking `#[idempotent]` out to let failed calls be retried, `#[named_params]` out
+/// to pass the arguments by name, `#[omit_trailing_none]` out to leave trailing `null` arguments
+/// out of arrays and `#[rpc(name = "...")]` out to call a differently named method.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! jsonrpc_client_method {
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] $params:tt $name:tt
+        #[idempotent]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_method!([$($attrs)*] [true] [$error_ty] $params $name $($rest)*);
+    );
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] $params:tt $name:tt
+        #[named_params]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_method!([$($attrs)*] [$idempotent] [$error_ty] [named ()] $name $($rest)*);
+    );
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] $params:tt $name:tt
+        #[named_params $renames:tt]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_method!(
+            [$($attrs)*] [$idempotent] [$error_ty] [named $renames] $name $($rest)*
+        );
+    );
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] [positional] $name:tt
+        #[omit_trailing_none]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_method!(
+            [$($attrs)*] [$idempotent] [$error_ty] [positional omit] $name $($rest)*
+        );
+    );
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] $params:tt $name:tt
+        #[omit_trailing_none]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_method!([$($attrs)*] [$idempotent] [$error_ty] $params $name $($rest)*);
+    );
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] $params:tt $name:tt
+        #[rpc(name = $rpc_name:expr)]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_method!(
+            [$($attrs)*] [$idempotent] [$error_ty] $params [name $rpc_name] $($rest)*
+        );
+    );
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] $params:tt $name:tt
+        #[$($attr:tt)*]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_client_method!(
+            [$($attrs)* #[$($attr)*]] [$idempotent] [$error_ty] $params $name $($rest)*
+        );
+    );
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] $params:tt $name:tt
+        $(pub $(($($restrict:tt)+))*)* fn $method:ident
+            $(<$($generic:tt $(:
+                $bound:tt $(:: $bound_seg:ident)* $(<$($bound_arg:ty),*>)*
+                $(+ $more_bound:tt $(:: $more_seg:ident)* $(<$($more_arg:ty),*>)*)*
+            )*),*>)*
+            (&$($receiver:ident)+ $(, $arg_name:ident: $arg_ty:ty)*)
+            -> Future<$return_ty:ty>
+            $(where $($where_ty:ty:
+                $where_bound:tt $(:: $where_seg:ident)* $(<$($where_arg:ty),*>)*
+                $(+ $where_more:tt $(:: $where_more_seg:ident)* $(<$($where_more_arg:ty),*>)*)*
+            ),+)*;
+    ) => (
+        $($attrs)*
+        $(pub $(($($restrict)+))*)* fn $method $(<$($generic),*>)*(
+            &$($receiver)+ $(, $arg_name: $arg_ty)*
+        ) -> $crate::RpcFuture<$return_ty, $error_ty>
+        where
+            $($($($generic:
+                $bound $(:: $bound_seg)* $(<$($bound_arg),*>)*
+                $(+ $more_bound $(:: $more_seg)* $(<$($more_arg),*>)*)*,
+            )*)*)*
+            $($($where_ty:
+                $where_bound $(:: $where_seg)* $(<$($where_arg),*>)*
+                $(+ $where_more $(:: $where_more_seg)* $(<$($where_more_arg),*>)*)*,
+            )+)*
+            $return_ty: Send + 'static,
+        {
+            let method = jsonrpc_client_method_name!($name $method);
+            let params = jsonrpc_client_params!($params $($arg_name),*);
+            $crate::RpcFuture::new($crate::Future::map_err(
+                jsonrpc_client_receiver!($($receiver)+)
+                    .client
+                    .send_method_call(method, params, None, $idempotent),
+                <$error_ty as From<$crate::Error>>::from,
+            ))
+        }
+    );
+    (
+        [$($attrs:tt)*] [$idempotent:expr] [$error_ty:ty] $params:tt $name:tt
+        $(pub $(($($restrict:tt)+))*)* notify fn $method:ident
+            $(<$($generic:tt $(:
+                $bound:tt $(:: $bound_seg:ident)* $(<$($bound_arg:ty),*>)*
+                $(+ $more_bound:tt $(:: $more_seg:ident)* $(<$($more_arg:ty),*>)*)*
+            )*),*>)*
+            (&$($receiver:ident)+ $(, $arg_name:ident: $arg_ty:ty)*)
+            $(where $($where_ty:ty:
+                $where_bound:tt $(:: $where_seg:ident)* $(<$($where_arg:ty),*>)*
+                $(+ $where_more:tt $(:: $where_more_seg:ident)* $(<$($where_more_arg:ty),*>)*)*
+            ),+)*;
+    ) => (
+        $($attrs)*
+        $(pub $(($($restrict)+))*)* fn $method $(<$($generic),*>)*(
+            &$($receiver)+ $(, $arg_name: $arg_ty)*
+        ) -> $crate::RpcFuture<(), $error_ty>
+        where
+            $($($($generic:
+                $bound $(:: $bound_seg)* $(<$($bound_arg),*>)*
+                $(+ $more_bound $(:: $more_seg)* $(<$($more_arg),*>)*)*,
+            )*)*)*
+            $($($where_ty:
+                $where_bound $(:: $where_seg)* $(<$($where_arg),*>)*
+                $(+ $where_more $(:: $where_more_seg)* $(<$($where_more_arg),*>)*)*,
+            )+)*
+        {
+            let method = jsonrpc_client_method_name!($name $method);
+            let params = jsonrpc_client_params!($params $($arg_name),*);
+            $crate::RpcFuture::new($crate::Future::map_err(
+                jsonrpc_client_receiver!($($receiver)+)
+                    .client
+                    .send_serialized_notification(method, params),
+                <$error_ty as From<$crate::Error>>::from,
+            ))
+        }
+    );
+}
+
+
+/// Generates the application error enum for `jsonrpc_client`. Every variant holds the JSON-RPC 2.0
+/// error object with the given code, all other errors end up in the `Other` variant.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! jsonrpc_client_error {
+    (
+        $(#[$error_attr:meta])*
+        pub enum $error_name:ident {$(
+            $(#[$variant_attr:meta])*
+            $variant:ident = $code:expr,
+        )*}
+    ) => (
+        $(#[$error_attr])*
+        #[derive(Debug)]
+        pub enum $error_name {
+            $(
+                $(#[$variant_attr])*
+                $variant($crate::rpc_error::RpcError),
             )*
+            /// Any error that does not map to one of the other variants.
+            Other($crate::Error),
+        }
+
+        impl From<$crate::Error> for $error_name {
+            fn from(error: $crate::Error) -> Self {
+                match error {
+                    $crate::Error($crate::ErrorKind::JsonRpcError(rpc_error), state) => {
+                        match rpc_error.code.code() {
+                            $(code if code == $code => $error_name::$variant(rpc_error),)*
+                            _ => $error_name::Other($crate::Error(
+                                $crate::ErrorKind::JsonRpcError(rpc_error),
+                                state,
+                            )),
+                        }
+                    }
+                    error => $error_name::Other(error),
+                }
+            }
+        }
+
+        impl ::std::fmt::Display for $error_name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                match *self {
+                    $(
+                        $error_name::$variant(ref error) => {
+                            write!(f, "{}: {}", stringify!($variant), error.message)
+                        }
+                    )*
+                    $error_name::Other(ref error) => ::std::fmt::Display::fmt(error, f),
+                }
+            }
+        }
+
+        impl ::std::error::Error for $error_name {
+            fn description(&self) -> &str {
+                match *self {
+                    $($error_name::$variant(ref error) => &error.message,)*
+                    $error_name::Other(ref error) => error.kind().description(),
+                }
+            }
+
+            fn cause(&self) -> Option<&::std::error::Error> {
+                match *self {
+                    $($error_name::$variant(_) => None,)*
+                    $error_name::Other(ref error) => Some(error),
+                }
+            }
         }
     )
 }
 
 
-/// Expands a variable list of parameters into its serializable form. Is needed to make the params
-/// of a nullary method equal to `[]` instead of `()` and thus make sure it serializes to `[]`
-/// instead of `null`.
+/// Returns the receiver of a method generated by `jsonrpc_client_method`, given the tokens after
+/// the `&` of `&self` or `&mut self`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! jsonrpc_client_receiver {
+    (mut $selff:ident) => ($selff);
+    ($selff:ident) => ($selff);
+}
+
+
+/// Returns the name of the RPC method called by a method generated by `jsonrpc_client_method`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! jsonrpc_client_method_name {
+    ([] $method:ident) => (String::from(stringify!($method)));
+    ([namespace $namespace:expr] $method:ident) => (
+        format!("{}.{}", $namespace, stringify!($method))
+    );
+    ([name $rpc_name:expr] $method:ident) => (String::from($rpc_name));
+}
+
+
+/// Serializes the arguments of a method generated by `jsonrpc_client_method`, either as an array
+/// or, for `#[named_params]`, as an object keyed by the argument names or their renames. Renamed
+/// arguments are referred to by name, so that renaming an argument that doesn't exist fails to
+/// compile.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! jsonrpc_client_params {
+    ([positional] $($arg_name:ident),*) => (
+        $crate::positional_parameters(vec![$($crate::serialize_parameter(&$arg_name)),*], false)
+    );
+    ([positional omit] $($arg_name:ident),*) => (
+        $crate::positional_parameters(vec![$($crate::serialize_parameter(&$arg_name)),*], true)
+    );
+    ([named ($($from:ident = $to:expr),*)] $($arg_name:ident),*) => ({
+        $(let _ = &$from;)*
+        let rename = |name: &'static str| -> &'static str {
+            $(if name == stringify!($from) { return $to; })*
+            name
+        };
+        $crate::named_parameters(vec![
+            $((rename(stringify!($arg_name)), $crate::serialize_parameter(&$arg_name))),*
+        ])
+    });
+}
+
+
+/// The companion of `jsonrpc_client`, for the calls and notifications the server sends to the
+/// client. Generates a trait with a method for each of them, to be implemented by the application.
+/// Transports that are a `DuplexTransport` deliver them to the
+/// [`Server`](server/struct.Server.html) given to `Client::with_server`, and the provided
+/// `register_handlers` method of the trait sets handlers on that server calling the
+/// implementation. The trait can't declare a method of that name itself.
+///
+/// Methods are declared like in `jsonrpc_client`, but always take `&self` and return a future
+/// failing with a JSON-RPC 2.0 error object, which is sent back to the server as it is. The
+/// arguments are deserialized from the positional parameters of the call and must be owned types.
+/// Methods marked with `#[named_params]` take them from parameters passed by name instead, and
+/// `#[named_params(arg = "name")]` takes an argument from a parameter with another name. Missing
+/// named parameters are deserialized from `null`, so optional arguments can be left out.
+/// Parameters that can't be deserialized are answered with an invalid params error without
+/// calling the implementation. Notifications are declared with `notify fn`, and errors returned
+/// by them are only logged. `#[rpc(name = "...")]` handles a method with another name than the
+/// Rust method.
+///
+/// The client and the server side of a connection are declared with separate macros, as they
+/// are separate items: the client struct wraps a `ClientHandle`, while the trait is implemented by
+/// the application and its handlers are registered on the `Server` before the client is created.
+///
+/// ```rust,ignore
+/// jsonrpc_server!(pub trait DaemonCallbacks {
+///     /// Asks the daemon whether it is still alive.
+///     fn ping(&self, payload: String) -> Future<String>;
+///
+///     #[rpc(name = "$/cancelRequest")]
+///     notify fn cancel(&self, id: u64);
+///
+///     #[named_params(uri = "textDocument")]
+///     notify fn opened(&self, uri: String, version: Option<u64>);
+/// });
+///
+/// let (mut server, server_handle) = Server::new();
+/// Daemon::new().register_handlers(&mut server)?;
+/// let (client, client_handle) = transport.with_server(server);
+/// ```
+#[macro_export]
+macro_rules! jsonrpc_server {
+    (
+        $(#[$trait_attr:meta])*
+        $(pub $(($($restrict:tt)+))*)* trait $trait_name:ident {$(
+            $(#[$($attr:tt)*])*
+            $($head:ident)+ (&self $(, $arg_name:ident: $arg_ty:ty)*)
+                $(-> Future<$return_ty:ty>)*;
+        )*}
+    ) => (
+        $(#[$trait_attr])*
+        $(pub $(($($restrict)+))*)* trait $trait_name {
+            $(
+                jsonrpc_server_method!(
+                    [declare] [] [positional] []
+                    $(#[$($attr)*])*
+                    $($head)+ (&self $(, $arg_name: $arg_ty)*) $(-> Future<$return_ty>)*;
+                );
+            )*
+
+            /// Sets handlers calling this implementation for all methods and notifications of the
+            /// trait on the given server.
+            fn register_handlers(
+                self,
+                server: &mut $crate::server::Server,
+            ) -> ::std::result::Result<(), $crate::server::HandlerSettingError>
+            where
+                Self: Sized + Send + Sync + 'static,
+            {
+                let implementation = ::std::sync::Arc::new(self);
+                $(
+                    jsonrpc_server_method!(
+                        [register server implementation] [] [positional] []
+                        $(#[$($attr)*])*
+                        $($head)+ (&self $(, $arg_name: $arg_ty)*) $(-> Future<$return_ty>)*;
+                    );
+                )*
+                Ok(())
+            }
+        }
+    );
+}
+
+
+/// Generates either the declaration of a single method for `jsonrpc_server`, or the statement
+/// setting its handler on a server. Collects the attributes of the method one at a time, taking
+/// `#[named_params]` out to take the arguments by name and `#[rpc(name = "...")]` out to handle a
+/// differently named method.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! jsonrpc_server_method {
+    (
+        $mode:tt [$($attrs:tt)*] $params:tt $name:tt
+        #[named_params]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_server_method!($mode [$($attrs)*] [named ()] $name $($rest)*);
+    );
+    (
+        $mode:tt [$($attrs:tt)*] $params:tt $name:tt
+        #[named_params $renames:tt]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_server_method!($mode [$($attrs)*] [named $renames] $name $($rest)*);
+    );
+    (
+        $mode:tt [$($attrs:tt)*] $params:tt $name:tt
+        #[rpc(name = $rpc_name:expr)]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_server_method!($mode [$($attrs)*] $params [name $rpc_name] $($rest)*);
+    );
+    (
+        $mode:tt [$($attrs:tt)*] $params:tt $name:tt
+        #[$($attr:tt)*]
+        $($rest:tt)*
+    ) => (
+        jsonrpc_server_method!($mode [$($attrs)* #[$($attr)*]] $params $name $($rest)*);
+    );
+    (
+        [declare] [$($attrs:tt)*] $params:tt $name:tt
+        fn $method:ident(&self $(, $arg_name:ident: $arg_ty:ty)*) -> Future<$return_ty:ty>;
+    ) => (
+        $($attrs)*
+        fn $method(
+            &self $(, $arg_name: $arg_ty)*
+        ) -> $crate::RpcFuture<$return_ty, $crate::rpc_error::RpcError>;
+    );
+    (
+        [declare] [$($attrs:tt)*] $params:tt $name:tt
+        notify fn $method:ident(&self $(, $arg_name:ident: $arg_ty:ty)*);
+    ) => (
+        $($attrs)*
+        fn $method(
+            &self $(, $arg_name: $arg_ty)*
+        ) -> $crate::RpcFuture<(), $crate::rpc_error::RpcError>;
+    );
+    (
+        [register $server:ident $implementation:ident] [$($attrs:tt)*] [positional] $name:tt
+        fn $method:ident(&self $(, $arg_name:ident: $arg_ty:ty)*) -> Future<$return_ty:ty>;
+    ) => ({
+        let implementation = $implementation.clone();
+        $server.add(
+            jsonrpc_client_method_name!($name $method),
+            $crate::server::Handler::method(move |($($arg_name,)*): ($($arg_ty,)*)| {
+                implementation.$method($($arg_name),*)
+            }),
+        )?;
+    });
+    (
+        [register $server:ident $implementation:ident] [$($attrs:tt)*] [positional] $name:tt
+        notify fn $method:ident(&self $(, $arg_name:ident: $arg_ty:ty)*);
+    ) => ({
+        let implementation = $implementation.clone();
+        $server.add(
+            jsonrpc_client_method_name!($name $method),
+            $crate::server::Handler::notification(move |($($arg_name,)*): ($($arg_ty,)*)| {
+                implementation.$method($($arg_name),*)
+            }),
+        )?;
+    });
+    (
+        [register $server:ident $implementation:ident] [$($attrs:tt)*] [named $renames:tt] $name:tt
+        fn $method:ident(&self $(, $arg_name:ident: $arg_ty:ty)*) -> Future<$return_ty:ty>;
+    ) => ({
+        let implementation = $implementation.clone();
+        $server.add(
+            jsonrpc_client_method_name!($name $method),
+            $crate::server::Handler::named_method(move |mut params| {
+                jsonrpc_server_named_args!(params $renames $($arg_name: $arg_ty),*);
+                Ok(implementation.$method($($arg_name),*))
+            }),
+        )?;
+    });
+    (
+        [register $server:ident $implementation:ident] [$($attrs:tt)*] [named $renames:tt] $name:tt
+        notify fn $method:ident(&self $(, $arg_name:ident: $arg_ty:ty)*);
+    ) => ({
+        let implementation = $implementation.clone();
+        $server.add(
+            jsonrpc_client_method_name!($name $method),
+            $crate::server::Handler::named_notification(move |mut params| {
+                jsonrpc_server_named_args!(params $renames $($arg_name: $arg_ty),*);
+                Ok(implementation.$method($($arg_name),*))
+            }),
+        )?;
+    });
+}
+
+
+/// Takes the arguments of a method generated by `jsonrpc_server_method` with `#[named_params]` out
+/// of the parameters of a call, binding each to a variable named after the argument. Parameters
+/// that can't be deserialized make the enclosing handler reply with an invalid params error.
+/// Renamed arguments are referred to by name, so that renaming an argument that doesn't exist
+/// fails to compile.
 #[doc(hidden)]
 #[macro_export]
-macro_rules! expand_params {
-    () => ([] as [(); 0]);
-    ($($arg_name:ident,)+) => (($($arg_name,)+))
+macro_rules! jsonrpc_server_named_args {
+    ($params:ident ($($from:ident = $to:expr),*) $($arg_name:ident: $arg_ty:ty),*) => (
+        let rename = |name: &'static str| -> &'static str {
+            $(if name == stringify!($from) { return $to; })*
+            name
+        };
+        $(
+            let $arg_name: $arg_ty =
+                $crate::server::take_named_param(&mut $params, rename(stringify!($arg_name)))?;
+        )*
+        $(let _ = &$from;)*
+    );
 }
@@ -0,0 +1,298 @@
+use super::{Error, ErrorKind, Result};
+
+use jsonrpc_core::types::Id;
+use serde_json::Value as JsonValue;
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+
+/// Upper bounds, in milliseconds, of the buckets of a `LatencyHistogram`. Latencies above the last
+/// bound are counted in an additional overflow bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 12] =
+    [1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// How a method call sent to the server ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallOutcome {
+    /// The server replied with a successful response.
+    Success,
+    /// The server replied with a JSON-RPC 2.0 error with the given code.
+    RpcError(i64),
+    /// No response arrived before the deadline of the call.
+    Timeout,
+    /// The future of the call was dropped before a response arrived.
+    Cancelled,
+    /// The call failed for any other reason.
+    Failed,
+}
+
+impl CallOutcome {
+    fn from_result(result: &Result<JsonValue>) -> Self {
+        match *result {
+            Ok(_) => CallOutcome::Success,
+            Err(Error(ErrorKind::JsonRpcError(ref error), _)) => {
+                CallOutcome::RpcError(error.code.code())
+            }
+            Err(Error(ErrorKind::Timeout, _)) => CallOutcome::Timeout,
+            Err(_) => CallOutcome::Failed,
+        }
+    }
+}
+
+/// Receives the metrics of the method calls made by a client. The recorder is invoked from within
+/// the client future, so implementations should not block.
+pub trait MetricsRecorder: Send + 'static {
+    /// A method call was sent to the server.
+    fn call_sent(&self, method: &str);
+
+    /// A method call that was sent to the server ended, `latency` after it was sent.
+    fn call_completed(&self, method: &str, latency: Duration, outcome: CallOutcome);
+
+    /// The number of method calls waiting for a response changed.
+    fn in_flight(&self, count: usize);
+
+    /// The server sent a response with an id that does not belong to any pending method call.
+    fn unknown_response(&self, id: &Id);
+}
+
+/// A histogram of call latencies with the fixed buckets given by `LATENCY_BUCKETS_MS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: [u64; 13],
+    count: u64,
+    total: Duration,
+}
+
+impl LatencyHistogram {
+    /// Creates an empty histogram.
+    pub fn new() -> Self {
+        LatencyHistogram {
+            buckets: [0; 13],
+            count: 0,
+            total: Duration::from_secs(0),
+        }
+    }
+
+    /// Adds a latency to the histogram.
+    pub fn record(&mut self, latency: Duration) {
+        let millis = latency.as_secs() * 1000 + u64::from(latency.subsec_millis());
+        let index = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| millis <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.total += latency;
+    }
+
+    /// Returns the number of latencies in each bucket. The last entry is the overflow bucket.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Returns the number of recorded latencies.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the sum of all recorded latencies.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The metrics of all calls to a single method.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodMetrics {
+    /// The number of calls sent to the server.
+    pub calls: u64,
+    /// The number of calls that got a successful response.
+    pub successes: u64,
+    /// The number of calls that got an error response, by error code.
+    pub errors: BTreeMap<i64, u64>,
+    /// The number of calls that timed out.
+    pub timeouts: u64,
+    /// The number of calls that were cancelled by dropping their future.
+    pub cancellations: u64,
+    /// The number of calls that failed for any other reason.
+    pub failures: u64,
+    /// The latencies of all calls that ended.
+    pub latency: LatencyHistogram,
+}
+
+/// A point in time view of the metrics collected by an `InMemoryRecorder`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// The metrics of each called method.
+    pub methods: HashMap<String, MethodMetrics>,
+    /// The number of calls currently waiting for a response.
+    pub in_flight: usize,
+    /// The number of responses received with an id that did not belong to any pending call.
+    pub unknown_responses: u64,
+}
+
+/// A recorder keeping all metrics in memory. Clones of the recorder share the same metrics, so a
+/// clone can be kept around to take snapshots of the metrics of a running client.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryRecorder {
+    metrics: Arc<Mutex<MetricsSnapshot>>,
+}
+
+impl InMemoryRecorder {
+    /// Creates a new recorder without any metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the current metrics.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> ::std::sync::MutexGuard<'_, MetricsSnapshot> {
+        match self.metrics.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl MetricsRecorder for InMemoryRecorder {
+    fn call_sent(&self, method: &str) {
+        let mut metrics = self.lock();
+        method_metrics(&mut metrics.methods, method).calls += 1;
+    }
+
+    fn call_completed(&self, method: &str, latency: Duration, outcome: CallOutcome) {
+        let mut metrics = self.lock();
+        let method_metrics = method_metrics(&mut metrics.methods, method);
+        match outcome {
+            CallOutcome::Success => method_metrics.successes += 1,
+            CallOutcome::RpcError(code) => {
+                *method_metrics.errors.entry(code).or_insert(0) += 1;
+            }
+            CallOutcome::Timeout => method_metrics.timeouts += 1,
+            CallOutcome::Cancelled => method_metrics.cancellations += 1,
+            CallOutcome::Failed => method_metrics.failures += 1,
+        }
+        method_metrics.latency.record(latency);
+    }
+
+    fn in_flight(&self, count: usize) {
+        self.lock().in_flight = count;
+    }
+
+    fn unknown_response(&self, _id: &Id) {
+        self.lock().unknown_responses += 1;
+    }
+}
+
+/// Returns the metrics of a method, adding empty metrics for methods seen for the first time.
+fn method_metrics<'a>(
+    methods: &'a mut HashMap<String, MethodMetrics>,
+    method: &str,
+) -> &'a mut MethodMetrics {
+    match methods.entry(method.to_owned()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => entry.insert(MethodMetrics::default()),
+    }
+}
+
+/// Keeps track of when and for which method the pending calls of a client were sent, and reports
+/// them to a recorder.
+pub(crate) struct CallMetrics {
+    recorder: Box<dyn MetricsRecorder>,
+    sent_calls: HashMap<Id, (String, Instant)>,
+}
+
+impl CallMetrics {
+    pub fn new(recorder: impl MetricsRecorder) -> Self {
+        CallMetrics {
+            recorder: Box::new(recorder),
+            sent_calls: HashMap::new(),
+        }
+    }
+
+    pub fn call_sent(&mut self, id: &Id, method: &str, in_flight: usize) {
+        self.sent_calls
+            .insert(id.clone(), (method.to_owned(), Instant::now()));
+        self.recorder.call_sent(method);
+        self.recorder.in_flight(in_flight);
+    }
+
+    pub fn call_completed(&mut self, id: &Id, result: &Result<JsonValue>, in_flight: usize) {
+        self.call_ended(id, CallOutcome::from_result(result), in_flight);
+    }
+
+    pub fn call_cancelled(&mut self, id: &Id, in_flight: usize) {
+        self.call_ended(id, CallOutcome::Cancelled, in_flight);
+    }
+
+    pub fn unknown_response(&mut self, id: &Id) {
+        self.recorder.unknown_response(id);
+    }
+
+    fn call_ended(&mut self, id: &Id, outcome: CallOutcome, in_flight: usize) {
+        if let Some((method, sent_at)) = self.sent_calls.remove(id) {
+            self.recorder
+                .call_completed(&method, sent_at.elapsed(), outcome);
+        }
+        self.recorder.in_flight(in_flight);
+    }
+}
+
+impl fmt::Debug for CallMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CallMetrics {{ sent_calls: {} }}", self.sent_calls.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets() {
+        let mut histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_millis(0));
+        histogram.record(Duration::from_millis(7));
+        histogram.record(Duration::from_secs(60));
+        assert_eq!(histogram.count(), 3);
+        assert_eq!(histogram.buckets()[0], 1);
+        assert_eq!(histogram.buckets()[2], 1);
+        assert_eq!(histogram.buckets()[LATENCY_BUCKETS_MS.len()], 1);
+    }
+
+    #[test]
+    fn records_calls() {
+        let recorder = InMemoryRecorder::new();
+        let mut metrics = CallMetrics::new(recorder.clone());
+        metrics.call_sent(&Id::Num(1), "foo", 1);
+        metrics.call_sent(&Id::Num(2), "foo", 2);
+        assert_eq!(recorder.snapshot().in_flight, 2);
+
+        let error = ErrorKind::JsonRpcError(::jsonrpc_core::Error::method_not_found());
+        metrics.call_completed(&Id::Num(1), &Err(error.into()), 1);
+        metrics.call_cancelled(&Id::Num(2), 0);
+        metrics.unknown_response(&Id::Num(3));
+
+        let snapshot = recorder.snapshot();
+        let foo = &snapshot.methods["foo"];
+        assert_eq!(foo.calls, 2);
+        assert_eq!(foo.errors.get(&-32601), Some(&1));
+        assert_eq!(foo.cancellations, 1);
+        assert_eq!(foo.latency.count(), 2);
+        assert_eq!(snapshot.in_flight, 0);
+        assert_eq!(snapshot.unknown_responses, 1);
+    }
+}
@@ -0,0 +1,135 @@
+use super::Error;
+
+use futures::future::{self, Either};
+use futures::Future;
+use jsonrpc_core::types::{MethodCall, Notification, Output};
+
+use std::fmt;
+use std::sync::Arc;
+
+
+/// The future returned by the methods of a [`Middleware`](trait.Middleware.html).
+pub type MiddlewareFuture<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
+
+/// What should be done with an outgoing method call after it has passed through a middleware.
+#[derive(Debug)]
+pub enum CallAction {
+    /// Pass the, possibly rewritten, method call on towards the server.
+    Send(MethodCall),
+    /// Don't send the method call to the server, but answer it with the given output instead.
+    Respond(Output),
+}
+
+/// A middleware sits between the handles of a client and the transport. It can inspect, rewrite,
+/// answer or reject outgoing method calls and notifications, as well as inspect and rewrite the
+/// responses coming back from the server.
+///
+/// All methods are asynchronous. If the returned future fails, the corresponding call or
+/// notification fails with the same error. The default implementations pass everything through
+/// unchanged.
+pub trait Middleware: Send + Sync + 'static {
+    /// Processes a method call on its way to the server.
+    fn method_call(&self, call: MethodCall) -> MiddlewareFuture<CallAction> {
+        Box::new(future::ok(CallAction::Send(call)))
+    }
+
+    /// Processes a notification on its way to the server. Resolving to `None` drops the
+    /// notification without sending it.
+    fn notification(&self, notification: Notification) -> MiddlewareFuture<Option<Notification>> {
+        Box::new(future::ok(Some(notification)))
+    }
+
+    /// Processes a response from the server before it is handed to the caller.
+    fn response(&self, output: Output) -> MiddlewareFuture<Output> {
+        Box::new(future::ok(output))
+    }
+}
+
+/// An ordered stack of middleware. Outgoing method calls and notifications pass through the layers
+/// in the order they were pushed, responses pass through them in the reverse order. If a layer
+/// answers a method call itself, the answer passes back through the layers before it, the same
+/// way a response from the server would.
+#[derive(Clone, Default)]
+pub struct MiddlewareStack {
+    layers: Arc<Vec<Arc<dyn Middleware>>>,
+}
+
+impl MiddlewareStack {
+    /// Creates an empty middleware stack.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new innermost layer to the stack.
+    pub fn push(&mut self, middleware: impl Middleware) {
+        let mut layers = (*self.layers).clone();
+        layers.push(Arc::new(middleware));
+        self.layers = Arc::new(layers);
+    }
+
+    /// Returns the number of layers in the stack.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns true if there are no layers in the stack.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    fn method_call_from(
+        layers: Arc<Vec<Arc<dyn Middleware>>>,
+        index: usize,
+        call: MethodCall,
+    ) -> MiddlewareFuture<CallAction> {
+        let layer = match layers.get(index) {
+            Some(layer) => layer.clone(),
+            None => return Box::new(future::ok(CallAction::Send(call))),
+        };
+
+        let processed = layer.method_call(call);
+        Box::new(processed.and_then(move |action| match action {
+            CallAction::Send(call) => {
+                let answered = Self::method_call_from(layers, index + 1, call);
+                Either::A(answered.and_then(move |action| match action {
+                    CallAction::Respond(output) => {
+                        Either::A(layer.response(output).map(CallAction::Respond))
+                    }
+                    action => Either::B(future::ok(action)),
+                }))
+            }
+            action => Either::B(future::ok(action)),
+        }))
+    }
+}
+
+impl Middleware for MiddlewareStack {
+    fn method_call(&self, call: MethodCall) -> MiddlewareFuture<CallAction> {
+        Self::method_call_from(self.layers.clone(), 0, call)
+    }
+
+    fn notification(&self, notification: Notification) -> MiddlewareFuture<Option<Notification>> {
+        let mut processed: MiddlewareFuture<_> = Box::new(future::ok(Some(notification)));
+        for layer in self.layers.iter().map(Arc::clone) {
+            processed = Box::new(processed.and_then(move |notification| match notification {
+                Some(notification) => Either::A(layer.notification(notification)),
+                None => Either::B(future::ok(None)),
+            }));
+        }
+        processed
+    }
+
+    fn response(&self, output: Output) -> MiddlewareFuture<Output> {
+        let mut processed: MiddlewareFuture<_> = Box::new(future::ok(output));
+        for layer in self.layers.iter().rev().map(Arc::clone) {
+            processed = Box::new(processed.and_then(move |output| layer.response(output)));
+        }
+        processed
+    }
+}
+
+impl fmt::Debug for MiddlewareStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MiddlewareStack {{ layers: {} }}", self.layers.len())
+    }
+}
@@ -0,0 +1,118 @@
+use super::Result;
+
+use futures::executor::{self, Notify};
+use futures::sync::oneshot;
+use futures::task::AtomicTask;
+use futures::Async;
+use jsonrpc_core::types::Id;
+use serde_json::Value as JsonValue;
+
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+
+type Completion = oneshot::Sender<Result<JsonValue>>;
+
+/// Keeps the completion channels of the method calls waiting for a response. Every call is
+/// watched for its future being dropped under its own wakeup token, so that finding the cancelled
+/// calls only looks at the calls that have been woken up instead of at every pending call.
+#[derive(Debug)]
+pub struct PendingCalls {
+    calls: HashMap<Id, (usize, Completion)>,
+    ids: HashMap<usize, Id>,
+    next_token: usize,
+    woken: Arc<WokenTokens>,
+}
+
+/// The tokens of the calls that have been woken up since they were last polled.
+#[derive(Debug, Default)]
+struct WokenTokens {
+    tokens: Mutex<Vec<usize>>,
+    task: AtomicTask,
+}
+
+impl WokenTokens {
+    fn push(&self, token: usize) {
+        match self.tokens.lock() {
+            Ok(mut tokens) => tokens.push(token),
+            Err(poisoned) => poisoned.into_inner().push(token),
+        }
+    }
+
+    fn take(&self) -> Vec<usize> {
+        match self.tokens.lock() {
+            Ok(mut tokens) => mem::replace(&mut *tokens, Vec::new()),
+            Err(poisoned) => mem::replace(&mut *poisoned.into_inner(), Vec::new()),
+        }
+    }
+}
+
+impl Notify for WokenTokens {
+    fn notify(&self, token: usize) {
+        self.push(token);
+        self.task.notify();
+    }
+}
+
+impl PendingCalls {
+    pub fn new() -> Self {
+        PendingCalls {
+            calls: HashMap::new(),
+            ids: HashMap::new(),
+            next_token: 0,
+            woken: Arc::new(WokenTokens::default()),
+        }
+    }
+
+    /// Adds a call. It is watched for being cancelled from the next time the calls are polled.
+    pub fn insert(&mut self, id: Id, completion: Completion) {
+        let token = self.next_token;
+        self.next_token = self.next_token.wrapping_add(1);
+        if let Some((token, _)) = self.calls.insert(id.clone(), (token, completion)) {
+            self.ids.remove(&token);
+        }
+        self.ids.insert(token, id);
+        self.woken.push(token);
+    }
+
+    /// Removes a call, returning its completion channel.
+    pub fn remove(&mut self, id: &Id) -> Option<Completion> {
+        let (token, completion) = self.calls.remove(id)?;
+        self.ids.remove(&token);
+        Some(completion)
+    }
+
+    pub fn contains_key(&self, id: &Id) -> bool {
+        self.calls.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Removes the calls whose futures have been dropped, returning their ids. The current task
+    /// is notified once the future of another call is dropped.
+    pub fn poll_cancelled(&mut self) -> Vec<Id> {
+        self.woken.task.register();
+        let mut cancelled_ids = Vec::new();
+        for token in self.woken.take() {
+            let id = match self.ids.get(&token) {
+                Some(id) => id.clone(),
+                // The call has been completed since it was woken up.
+                None => continue,
+            };
+            let cancelled = match self.calls.get_mut(&id) {
+                Some(&mut (_, ref mut completion)) => {
+                    executor::with_notify(&self.woken, token, || completion.poll_cancel())
+                }
+                None => continue,
+            };
+            if let Ok(Async::Ready(())) = cancelled {
+                self.remove(&id);
+                cancelled_ids.push(id);
+            }
+        }
+        cancelled_ids
+    }
+}
@@ -0,0 +1,201 @@
+use jsonrpc_core::types::{Error as RpcError, ErrorCode, Version};
+use serde;
+use serde_json::{self, Map, Value as JsonValue};
+
+
+/// The version of the JSON-RPC protocol a client speaks with the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    /// JSON-RPC 1.0. Requests are sent without a `jsonrpc` member and always with `params`, and
+    /// notifications are sent as requests with a `null` id. Responses are accepted with both a
+    /// `result` and an `error` member, one of them `null`. Errors that are not JSON-RPC 2.0 error
+    /// objects are turned into one with the code `LEGACY_ERROR_CODE`, a message describing the
+    /// error and the original error as data.
+    V1,
+    /// JSON-RPC 2.0. Responses without `"jsonrpc": "2.0"` are rejected with
+    /// `ErrorKind::InvalidVersion`. This is the default.
+    V2,
+    /// JSON-RPC 2.0, but responses without a `jsonrpc` member are accepted as well.
+    V2Lenient,
+}
+
+/// The error code of the JSON-RPC 2.0 errors that JSON-RPC 1.0 errors are turned into, when they
+/// are not JSON-RPC 2.0 error objects already.
+pub const LEGACY_ERROR_CODE: i64 = -32000;
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        ProtocolVersion::V2
+    }
+}
+
+impl ProtocolVersion {
+    /// Returns the value of the `jsonrpc` member of outgoing messages.
+    pub(crate) fn version(self) -> Option<Version> {
+        match self {
+            ProtocolVersion::V1 => None,
+            ProtocolVersion::V2 | ProtocolVersion::V2Lenient => Some(Version::V2),
+        }
+    }
+
+    /// Returns true if a response with the given `jsonrpc` member is acceptable.
+    pub(crate) fn accepts(self, version: Option<Version>) -> bool {
+        match self {
+            ProtocolVersion::V1 | ProtocolVersion::V2Lenient => true,
+            ProtocolVersion::V2 => version == Some(Version::V2),
+        }
+    }
+
+    /// Serializes an outgoing message, converting it to a JSON-RPC 1.0 envelope if needed.
+    pub(crate) fn serialize(self, message: &impl serde::Serialize) -> serde_json::Result<String> {
+        match self {
+            ProtocolVersion::V1 => {
+                let mut message = serde_json::to_value(message)?;
+                for_each_entry(&mut message, to_v1_envelope);
+                serde_json::to_string(&message)
+            }
+            ProtocolVersion::V2 | ProtocolVersion::V2Lenient => serde_json::to_string(message),
+        }
+    }
+
+    /// Deserializes an incoming message, converting it from a JSON-RPC 1.0 envelope if needed.
+    pub(crate) fn deserialize<T>(self, payload: &str) -> serde_json::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match self {
+            ProtocolVersion::V1 => {
+                let mut message: JsonValue = serde_json::from_str(payload)?;
+                for_each_entry(&mut message, from_v1_envelope);
+                serde_json::from_value(message)
+            }
+            ProtocolVersion::V2 | ProtocolVersion::V2Lenient => serde_json::from_str(payload),
+        }
+    }
+}
+
+/// Applies the given conversion to a message, or to every entry of a batch.
+fn for_each_entry(message: &mut JsonValue, convert: fn(&mut Map<String, JsonValue>)) {
+    match *message {
+        JsonValue::Array(ref mut entries) => {
+            for entry in entries {
+                for_each_entry(entry, convert);
+            }
+        }
+        JsonValue::Object(ref mut entry) => convert(entry),
+        _ => (),
+    }
+}
+
+fn to_v1_envelope(entry: &mut Map<String, JsonValue>) {
+    entry.remove("jsonrpc");
+    if entry.contains_key("method") {
+        if entry.get("params").map_or(true, JsonValue::is_null) {
+            entry.insert("params".to_owned(), JsonValue::Array(Vec::new()));
+        }
+        entry.entry("id").or_insert(JsonValue::Null);
+    } else {
+        entry.entry("result").or_insert(JsonValue::Null);
+        entry.entry("error").or_insert(JsonValue::Null);
+    }
+}
+
+fn from_v1_envelope(entry: &mut Map<String, JsonValue>) {
+    entry.remove("jsonrpc");
+    if entry.contains_key("method") {
+        // Notifications are requests with a null id.
+        if entry.get("id") == Some(&JsonValue::Null) {
+            entry.remove("id");
+        }
+        return;
+    }
+    let error = match entry.remove("error") {
+        None | Some(JsonValue::Null) => return,
+        Some(error) => error,
+    };
+    let error = match serde_json::from_value::<RpcError>(error.clone()) {
+        Ok(_) => error,
+        Err(_) => {
+            let message = match error {
+                JsonValue::String(ref message) => message.clone(),
+                ref error => error.to_string(),
+            };
+            let legacy_error = RpcError {
+                code: ErrorCode::ServerError(LEGACY_ERROR_CODE),
+                message,
+                data: Some(error),
+            };
+            serde_json::to_value(legacy_error).unwrap_or(JsonValue::Null)
+        }
+    };
+    entry.remove("result");
+    entry.insert("error".to_owned(), error);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::types::{Id, MethodCall, Notification, Output};
+
+    fn json(value: &str) -> JsonValue {
+        serde_json::from_str(value).unwrap()
+    }
+
+    fn result(output: Output) -> ::std::result::Result<JsonValue, RpcError> {
+        output.into()
+    }
+
+    #[test]
+    fn v1_requests() {
+        let call = MethodCall {
+            jsonrpc: None,
+            method: "getblockcount".to_owned(),
+            params: None,
+            id: Id::Num(1),
+        };
+        let notification = Notification {
+            jsonrpc: None,
+            method: "ping".to_owned(),
+            params: None,
+        };
+        assert_eq!(
+            json(&ProtocolVersion::V1.serialize(&call).unwrap()),
+            json(r#"{"method": "getblockcount", "params": [], "id": 1}"#)
+        );
+        assert_eq!(
+            json(&ProtocolVersion::V1.serialize(&notification).unwrap()),
+            json(r#"{"method": "ping", "params": [], "id": null}"#)
+        );
+    }
+
+    #[test]
+    fn v1_responses() {
+        let success: Output = ProtocolVersion::V1
+            .deserialize(r#"{"result": 5, "error": null, "id": 1}"#)
+            .unwrap();
+        assert_eq!(success.version(), None);
+        assert_eq!(result(success), Ok(json("5")));
+
+        let failure: Output = ProtocolVersion::V1
+            .deserialize(r#"{"result": null, "error": {"code": -5, "message": "No"}, "id": 1}"#)
+            .unwrap();
+        assert_eq!(
+            result(failure).unwrap_err().code,
+            ErrorCode::ServerError(-5)
+        );
+
+        let legacy_failure: Output = ProtocolVersion::V1
+            .deserialize(r#"{"result": null, "error": "Out of cheese", "id": 1}"#)
+            .unwrap();
+        let error = result(legacy_failure).unwrap_err();
+        assert_eq!(error.code, ErrorCode::ServerError(LEGACY_ERROR_CODE));
+        assert_eq!(error.message, "Out of cheese");
+    }
+
+    #[test]
+    fn accepted_versions() {
+        assert!(!ProtocolVersion::V2.accepts(None));
+        assert!(ProtocolVersion::V2Lenient.accepts(None));
+        assert!(ProtocolVersion::V2Lenient.accepts(Some(Version::V2)));
+    }
+}
@@ -0,0 +1,258 @@
+use super::OutgoingMessage;
+
+use futures::{Async, Future};
+use tokio_timer::{self, Delay};
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+
+/// Limits how many method calls a client has in flight and how many requests it sends per second.
+/// Messages that are over the limit are sent in the order they were made, no matter which
+/// `ClientHandle` they were made through. While a message is held back the client stops taking
+/// new messages from its handles, so that their calls wait for room as well. Responses to
+/// requests from the server are never held back.
+///
+/// The deadline of a call starts when the call is sent, not while it waits for the limit. Waiting
+/// for the token bucket to refill is implemented with `tokio-timer`, so the client has to be driven
+/// on an executor that provides a timer.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    max_in_flight: Option<usize>,
+    requests_per_second: Option<u32>,
+    burst: Option<u32>,
+}
+
+impl RateLimit {
+    /// Constructs a new rate limit that doesn't limit anything yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of method calls waiting for a response. A batch is sent as long as
+    /// the limit is not reached, even if its calls exceed the limit.
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    /// Sets the rate at which method calls, notifications and batches are sent, using a token
+    /// bucket that refills at the given number of tokens per second.
+    pub fn requests_per_second(mut self, requests_per_second: u32) -> Self {
+        self.requests_per_second = Some(requests_per_second);
+        self
+    }
+
+    /// Sets the size of the token bucket, meaning the number of requests that can be sent at once
+    /// after the client has been idle. Defaults to the number of requests per second.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = Some(burst);
+        self
+    }
+}
+
+/// The queue of messages held back by the rate limit of a client.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    max_in_flight: Option<usize>,
+    bucket: Option<TokenBucket>,
+    queue: VecDeque<OutgoingMessage>,
+    waiting_for_room: bool,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        RateLimiter {
+            max_in_flight: limit.max_in_flight,
+            bucket: limit.requests_per_second.map(|requests_per_second| {
+                TokenBucket::new(
+                    requests_per_second,
+                    limit.burst.unwrap_or(requests_per_second),
+                )
+            }),
+            queue: VecDeque::new(),
+            waiting_for_room: false,
+        }
+    }
+
+    /// Puts a message at the end of the queue. Responses don't have to wait, so they are handed
+    /// back right away.
+    pub fn hold(&mut self, message: OutgoingMessage) -> Option<OutgoingMessage> {
+        match message {
+            OutgoingMessage::Response(_) => Some(message),
+            message => {
+                self.queue.push_back(message);
+                None
+            }
+        }
+    }
+
+    /// Returns the next message that may be sent, given the number of calls that are currently in
+    /// flight. Calls whose futures have been dropped while waiting in the queue are skipped. If
+    /// no message may be sent before the token bucket refills, the current task is notified once
+    /// it has.
+    pub fn poll_next(
+        &mut self,
+        in_flight: usize,
+    ) -> Result<Option<OutgoingMessage>, tokio_timer::Error> {
+        self.waiting_for_room = false;
+        loop {
+            let cancelled = match self.queue.front() {
+                Some(OutgoingMessage::RpcCall(_, _, completion, _)) => completion.is_canceled(),
+                Some(_) => false,
+                None => return Ok(None),
+            };
+            if cancelled {
+                trace!("Future for rate limited RPC call dropped before it was sent");
+                self.queue.pop_front();
+                continue;
+            }
+            if self.is_full(in_flight) {
+                self.waiting_for_room = true;
+                return Ok(None);
+            }
+            if let Some(ref mut bucket) = self.bucket {
+                if !bucket.poll_take()? {
+                    return Ok(None);
+                }
+            }
+            return Ok(self.queue.pop_front());
+        }
+    }
+
+    /// Returns true if messages are waiting for the limit.
+    pub fn is_holding(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Takes the next message from the queue regardless of the limits.
+    pub fn pop(&mut self) -> Option<OutgoingMessage> {
+        self.queue.pop_front()
+    }
+
+    /// Returns true if the queue was held back by the number of calls in flight the last time it
+    /// was polled, but there is room for another call now.
+    pub fn has_room(&self, in_flight: usize) -> bool {
+        self.waiting_for_room && !self.is_full(in_flight)
+    }
+
+    fn is_full(&self, in_flight: usize) -> bool {
+        self.max_in_flight
+            .map(|max_in_flight| in_flight >= max_in_flight)
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    interval: Duration,
+    capacity: u32,
+    tokens: u32,
+    last_refill: Instant,
+    delay: Option<Delay>,
+}
+
+impl TokenBucket {
+    fn new(requests_per_second: u32, capacity: u32) -> Self {
+        let requests_per_second = cmp::max(requests_per_second, 1);
+        let capacity = cmp::max(capacity, 1);
+        TokenBucket {
+            interval: Duration::from_secs(1) / requests_per_second,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+            delay: None,
+        }
+    }
+
+    /// Takes a token from the bucket if there is one. Otherwise the current task is notified once
+    /// the next token has been added.
+    fn poll_take(&mut self) -> Result<bool, tokio_timer::Error> {
+        loop {
+            self.refill(Instant::now());
+            if self.tokens > 0 {
+                self.tokens -= 1;
+                self.delay = None;
+                return Ok(true);
+            }
+            let next_token = self.last_refill + self.interval;
+            let delay = self.delay.get_or_insert_with(|| Delay::new(next_token));
+            if delay.deadline() != next_token {
+                delay.reset(next_token);
+            }
+            if let Async::NotReady = delay.poll()? {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if self.tokens >= self.capacity {
+            self.last_refill = now;
+            return;
+        }
+        while self.tokens < self.capacity && self.last_refill + self.interval <= now {
+            self.tokens += 1;
+            self.last_refill += self.interval;
+        }
+        if self.tokens >= self.capacity {
+            self.last_refill = now;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::sync::oneshot;
+    use serde_json::Value as JsonValue;
+    use Result;
+
+    fn call(method: &str) -> (OutgoingMessage, oneshot::Receiver<Result<JsonValue>>) {
+        let (tx, rx) = oneshot::channel();
+        let call = OutgoingMessage::RpcCall(method.to_owned(), None, tx, None);
+        (call, rx)
+    }
+
+    #[test]
+    fn limits_calls_in_flight() {
+        let mut limiter = RateLimiter::new(RateLimit::new().max_in_flight(1));
+        let (first, _first_rx) = call("first");
+        let (second, second_rx) = call("second");
+        let (third, _third_rx) = call("third");
+        assert!(limiter.hold(first).is_none());
+        assert!(limiter.hold(second).is_none());
+        assert!(limiter.hold(third).is_none());
+
+        match limiter.poll_next(0).unwrap() {
+            Some(OutgoingMessage::RpcCall(ref method, ..)) => assert_eq!(method, "first"),
+            message => panic!("Expected the first call, got {:?}", message),
+        }
+        assert!(limiter.poll_next(1).unwrap().is_none());
+        assert!(!limiter.has_room(1));
+        assert!(limiter.has_room(0));
+
+        // The second call was cancelled while it was waiting.
+        drop(second_rx);
+        match limiter.poll_next(0).unwrap() {
+            Some(OutgoingMessage::RpcCall(ref method, ..)) => assert_eq!(method, "third"),
+            message => panic!("Expected the third call, got {:?}", message),
+        }
+    }
+
+    #[test]
+    fn token_bucket_refills() {
+        let mut bucket = TokenBucket::new(10, 2);
+        let start = bucket.last_refill;
+        bucket.tokens = 0;
+
+        bucket.refill(start + Duration::from_millis(50));
+        assert_eq!(bucket.tokens, 0);
+        bucket.refill(start + Duration::from_millis(150));
+        assert_eq!(bucket.tokens, 1);
+        bucket.refill(start + Duration::from_secs(10));
+        assert_eq!(bucket.tokens, 2);
+    }
+}
@@ -0,0 +1,425 @@
+use super::{DuplexTransport, ErrorKind, Transport};
+use backoff::BackoffPolicy;
+use call_failures::CallFailures;
+
+use futures::{Async, AsyncSink, Future, IntoFuture, Poll, Sink, StartSend, Stream};
+use jsonrpc_core::types::{Call, Id, MethodCall, Request, Response};
+use serde_json;
+use tokio_timer::Delay;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+
+/// Opens new connections to the server.
+pub trait Connect: Send + 'static {
+    /// The transport of a single connection.
+    type Transport: Transport + 'static;
+    /// A future resolving to a new connection.
+    type Future: Future<Item = Self::Transport, Error = <Self::Transport as Transport>::Error>
+        + Send
+        + 'static;
+
+    /// Starts opening a new connection.
+    fn connect(&mut self) -> Self::Future;
+}
+
+impl<F, R, T> Connect for F
+where
+    F: FnMut() -> R + Send + 'static,
+    R: IntoFuture<Item = T, Error = <T as Transport>::Error>,
+    R::Future: Send + 'static,
+    T: Transport + 'static,
+{
+    type Transport = T;
+    type Future = R::Future;
+
+    fn connect(&mut self) -> Self::Future {
+        self().into_future()
+    }
+}
+
+type ReplayPredicate = Box<dyn Fn(&MethodCall) -> bool + Send>;
+
+/// A transport that opens a new connection whenever the current one is lost, so that the client
+/// and its handles keep working across reconnects. Reconnection attempts are spaced out according
+/// to a backoff policy. Once the policy gives up, the transport fails with the last connection
+/// error, or finishes if the last connection was closed by the server.
+///
+/// Method calls that were sent but not answered when the connection was lost are either sent again
+/// on the new connection in the order they were first sent, or fail with
+/// `ErrorKind::ConnectionLost`. By default all of them fail, since the server might already have
+/// acted upon them.
+///
+/// Waiting between reconnection attempts is implemented with `tokio-timer`, so the client has to
+/// be driven on an executor that provides a timer.
+pub struct ReconnectingTransport<C: Connect, B: BackoffPolicy> {
+    connect: C,
+    backoff: B,
+    replay: ReplayPredicate,
+    call_failures: Option<CallFailures>,
+}
+
+impl<C: Connect, B: BackoffPolicy> ReconnectingTransport<C, B> {
+    /// Constructs a new reconnecting transport. The first connection is opened once the client
+    /// starts running.
+    pub fn new(connect: C, backoff: B) -> Self {
+        ReconnectingTransport {
+            connect,
+            backoff,
+            replay: Box::new(|_| false),
+            call_failures: None,
+        }
+    }
+
+    /// Sets the predicate deciding which of the calls pending at the moment the connection was lost
+    /// are sent again on the next connection. The other pending calls fail.
+    pub fn replay_calls<F>(mut self, replay: F) -> Self
+    where
+        F: Fn(&MethodCall) -> bool + Send + 'static,
+    {
+        self.replay = Box::new(replay);
+        self
+    }
+}
+
+impl<C: Connect, B: BackoffPolicy> fmt::Debug for ReconnectingTransport<C, B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ReconnectingTransport")
+    }
+}
+
+impl<C: Connect, B: BackoffPolicy> Transport for ReconnectingTransport<C, B> {
+    type Error = <C::Transport as Transport>::Error;
+    type Sink = ReconnectingSink<C, B>;
+    type Stream = ReconnectingStream<C, B>;
+
+    fn io_pair(self) -> (Self::Sink, Self::Stream) {
+        let connection = Arc::new(Mutex::new(Connection {
+            connect: self.connect,
+            backoff: self.backoff,
+            replay: self.replay,
+            call_failures: self.call_failures,
+            state: State::Disconnected,
+            attempt: 0,
+            last_error: None,
+            pending_calls: HashMap::new(),
+            next_sequence: 0,
+            outbox: VecDeque::new(),
+        }));
+        (
+            ReconnectingSink(connection.clone()),
+            ReconnectingStream(connection),
+        )
+    }
+
+    fn set_call_failures(&mut self, call_failures: CallFailures) {
+        self.call_failures = Some(call_failures);
+    }
+}
+
+impl<C, B> DuplexTransport for ReconnectingTransport<C, B>
+where
+    C: Connect,
+    C::Transport: DuplexTransport,
+    B: BackoffPolicy,
+{
+}
+
+/// The sending half of a `ReconnectingTransport`.
+pub struct ReconnectingSink<C: Connect, B: BackoffPolicy>(Arc<Mutex<Connection<C, B>>>);
+
+/// The receiving half of a `ReconnectingTransport`.
+pub struct ReconnectingStream<C: Connect, B: BackoffPolicy>(Arc<Mutex<Connection<C, B>>>);
+
+impl<C: Connect, B: BackoffPolicy> Sink for ReconnectingSink<C, B> {
+    type SinkItem = String;
+    type SinkError = <C::Transport as Transport>::Error;
+
+    fn start_send(&mut self, payload: String) -> StartSend<String, Self::SinkError> {
+        let mut connection = lock(&self.0);
+        connection.flush_outbox()?;
+        if !connection.outbox.is_empty() {
+            return Ok(AsyncSink::NotReady(payload));
+        }
+        connection.outbox.push_back(payload);
+        connection.flush_outbox()?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        let mut connection = lock(&self.0);
+        loop {
+            connection.flush_outbox()?;
+            if !connection.outbox.is_empty() {
+                return Ok(Async::NotReady);
+            }
+            if let Async::NotReady = connection.poll_connected()? {
+                return Ok(Async::NotReady);
+            }
+            let result = match connection.state {
+                State::Connected(ref mut sink, _) => sink.poll_complete(),
+                _ => unreachable!("Flushed a closed connection"),
+            };
+            match result {
+                // Goes on to poll the next connection attempt, so that the task is woken up once it
+                // is due.
+                Err(e) => connection.disconnected(Some(e)),
+                ready => return ready,
+            }
+        }
+    }
+
+    fn close(&mut self) -> Poll<(), Self::SinkError> {
+        let mut connection = lock(&self.0);
+        match connection.state {
+            State::Connected(ref mut sink, _) => sink.close(),
+            _ => Ok(Async::Ready(())),
+        }
+    }
+}
+
+impl<C: Connect, B: BackoffPolicy> Stream for ReconnectingStream<C, B> {
+    type Item = String;
+    type Error = <C::Transport as Transport>::Error;
+
+    fn poll(&mut self) -> Poll<Option<String>, Self::Error> {
+        let mut connection = lock(&self.0);
+        loop {
+            // The server closed the last connection and no new one will be opened.
+            if let State::Closed = connection.state {
+                if connection.last_error.is_none() {
+                    return Ok(Async::Ready(None));
+                }
+            }
+            if let Async::NotReady = connection.poll_connected()? {
+                return Ok(Async::NotReady);
+            }
+            let result = match connection.state {
+                State::Connected(_, ref mut stream) => stream.poll(),
+                _ => unreachable!("Polled the stream of a closed connection"),
+            };
+            match result {
+                Ok(Async::Ready(Some(payload))) => {
+                    connection.received(&payload);
+                    return Ok(Async::Ready(Some(payload)));
+                }
+                Ok(Async::Ready(None)) => connection.disconnected(None),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => connection.disconnected(Some(e)),
+            }
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+type ConnectFuture<T> = Box<dyn Future<Item = T, Error = <T as Transport>::Error> + Send + 'static>;
+
+enum State<T: Transport> {
+    Disconnected,
+    Waiting(Delay),
+    Connecting(ConnectFuture<T>),
+    Connected(T::Sink, T::Stream),
+    Closed,
+}
+
+/// A method call that has been sent on the current connection and not yet been answered.
+struct PendingCall {
+    payload: String,
+    replay: bool,
+    // Orders the calls by when they were sent.
+    sequence: u64,
+}
+
+struct Connection<C: Connect, B: BackoffPolicy> {
+    connect: C,
+    backoff: B,
+    replay: ReplayPredicate,
+    call_failures: Option<CallFailures>,
+    state: State<C::Transport>,
+    attempt: u32,
+    last_error: Option<<C::Transport as Transport>::Error>,
+    pending_calls: HashMap<Id, PendingCall>,
+    next_sequence: u64,
+    // Payloads waiting to be sent on the current or next connection.
+    outbox: VecDeque<String>,
+}
+
+impl<C: Connect, B: BackoffPolicy> Connection<C, B> {
+    /// Drives the connection until it is established. Fails with the last connection error once
+    /// the backoff policy gives up.
+    fn poll_connected(&mut self) -> Poll<(), <C::Transport as Transport>::Error> {
+        loop {
+            let next_state = match self.state {
+                State::Connected(..) => return Ok(Async::Ready(())),
+                State::Closed => {
+                    return match self.last_error.take() {
+                        Some(e) => Err(e),
+                        None => Ok(Async::NotReady),
+                    };
+                }
+                State::Disconnected => State::Connecting(Box::new(self.connect.connect())),
+                State::Waiting(ref mut delay) => match delay.poll() {
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Ok(Async::Ready(())) => State::Disconnected,
+                    Err(e) => {
+                        error!("Unable to wait before reconnecting: {}", e);
+                        State::Disconnected
+                    }
+                },
+                State::Connecting(ref mut connecting) => match connecting.poll() {
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Ok(Async::Ready(mut transport)) => {
+                        debug!("Connected to the server");
+                        if let Some(ref call_failures) = self.call_failures {
+                            transport.set_call_failures(call_failures.clone());
+                        }
+                        self.attempt = 0;
+                        self.last_error = None;
+                        let (sink, stream) = transport.io_pair();
+                        State::Connected(sink, stream)
+                    }
+                    Err(e) => {
+                        warn!("Unable to connect to the server: {}", e);
+                        self.last_error = Some(e);
+                        self.reconnect_later()
+                    }
+                },
+            };
+            self.state = next_state;
+        }
+    }
+
+    /// Returns the state to wait in before the next connection attempt, according to the backoff
+    /// policy.
+    fn reconnect_later(&mut self) -> State<C::Transport> {
+        match self.backoff.delay(self.attempt) {
+            Some(delay) => {
+                self.attempt += 1;
+                State::Waiting(Delay::new(Instant::now() + delay))
+            }
+            None => {
+                warn!("Giving up on reconnecting to the server");
+                State::Closed
+            }
+        }
+    }
+
+    /// Hands as many waiting payloads as possible to the current connection.
+    fn flush_outbox(&mut self) -> Result<(), <C::Transport as Transport>::Error> {
+        while !self.outbox.is_empty() {
+            if let Async::NotReady = self.poll_connected()? {
+                return Ok(());
+            }
+            let payload = self.outbox.pop_front().expect("outbox is not empty");
+            let result = match self.state {
+                State::Connected(ref mut sink, _) => sink.start_send(payload.clone()),
+                _ => unreachable!("Sent a payload on a closed connection"),
+            };
+            match result {
+                Ok(AsyncSink::Ready) => self.sent(&payload),
+                Ok(AsyncSink::NotReady(payload)) => {
+                    self.outbox.push_front(payload);
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.outbox.push_front(payload);
+                    self.disconnected(Some(e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts tracking the method calls in a payload that has been sent to the server.
+    fn sent(&mut self, payload: &str) {
+        let calls = match serde_json::from_str(payload) {
+            Ok(Request::Single(call)) => vec![call],
+            Ok(Request::Batch(calls)) => calls,
+            Err(_) => return,
+        };
+        for call in calls {
+            if let Call::MethodCall(method_call) = call {
+                // JSON-RPC 1.0 notifications are method calls with a null id, and never answered.
+                if method_call.id == Id::Null {
+                    continue;
+                }
+                let replay = (self.replay)(&method_call);
+                let id = method_call.id.clone();
+                let request = Request::Single(Call::MethodCall(method_call));
+                match serde_json::to_string(&request) {
+                    Ok(payload) => {
+                        let sequence = self.next_sequence;
+                        self.next_sequence += 1;
+                        self.pending_calls.insert(
+                            id,
+                            PendingCall {
+                                payload,
+                                replay,
+                                sequence,
+                            },
+                        );
+                    }
+                    Err(e) => error!("Unable to serialize call {:?} for replay: {}", id, e),
+                }
+            }
+        }
+    }
+
+    /// Stops tracking the method calls answered in a payload received from the server.
+    fn received(&mut self, payload: &str) {
+        match serde_json::from_str(payload) {
+            Ok(Response::Single(output)) => {
+                self.pending_calls.remove(output.id());
+            }
+            Ok(Response::Batch(outputs)) => {
+                for output in outputs {
+                    self.pending_calls.remove(output.id());
+                }
+            }
+            Err(_) => (),
+        }
+    }
+
+    /// Drops the current connection and decides the fate of the calls pending on it.
+    fn disconnected(&mut self, error: Option<<C::Transport as Transport>::Error>) {
+        match error {
+            Some(ref e) => warn!("Lost the connection to the server: {}", e),
+            None => warn!("The server closed the connection"),
+        }
+        self.last_error = error;
+        self.state = self.reconnect_later();
+
+        let mut pending_calls: Vec<_> = self.pending_calls.drain().collect();
+        pending_calls.sort_by_key(|(_, call)| call.sequence);
+        // Replayed calls go before the payloads that were not sent yet, in the order they were
+        // first sent.
+        let mut replayed = VecDeque::new();
+        for (id, call) in pending_calls {
+            if call.replay {
+                debug!("Replaying call {:?} on the next connection", id);
+                replayed.push_back(call.payload);
+            } else {
+                match self.call_failures {
+                    Some(ref call_failures) => {
+                        call_failures.fail(id, ErrorKind::ConnectionLost.into())
+                    }
+                    None => warn!(
+                        "Unable to fail call {:?} pending on the lost connection",
+                        id
+                    ),
+                }
+            }
+        }
+        replayed.append(&mut self.outbox);
+        self.outbox = replayed;
+    }
+}
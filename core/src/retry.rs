@@ -0,0 +1,149 @@
+use super::{ClientHandle, Error};
+use backoff::{BackoffPolicy, ExponentialBackoff};
+
+use futures::future::{self, Either, Loop};
+use futures::Future;
+use jsonrpc_core::types::Params;
+use serde_json::Value as JsonValue;
+use tokio_timer::Delay;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+
+type RetryPredicate = Box<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// Decides which failed method calls are sent again, and when. Only idempotent methods are ever
+/// retried, meaning methods that are marked as idempotent in the `jsonrpc_client` macro, that are
+/// invoked through `ClientHandle::call_idempotent_method` or that are on the allowlist of the
+/// policy.
+///
+/// Waiting between attempts is implemented with `tokio-timer`, so the calls have to be driven on
+/// an executor that provides a timer.
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Mutex<Box<dyn BackoffPolicy>>,
+    retryable: RetryPredicate,
+    idempotent_methods: HashSet<String>,
+}
+
+impl RetryPolicy {
+    /// Constructs a new retry policy. By default a call is attempted at most three times, errors
+    /// are retried if `Error::is_retryable` returns true and the time between attempts grows
+    /// exponentially from 100 milliseconds, with jitter.
+    pub fn new() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff: Mutex::new(Box::new(
+                ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(10))
+                    .jitter(),
+            )),
+            retryable: Box::new(Error::is_retryable),
+            idempotent_methods: HashSet::new(),
+        }
+    }
+
+    /// Sets the maximum number of times a call is attempted, including the first attempt.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the policy deciding how long to wait before each retry.
+    pub fn backoff(mut self, backoff: impl BackoffPolicy) -> Self {
+        self.backoff = Mutex::new(Box::new(backoff));
+        self
+    }
+
+    /// Sets the predicate deciding which errors are worth retrying a call for.
+    pub fn retry_if<F>(mut self, retryable: F) -> Self
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        self.retryable = Box::new(retryable);
+        self
+    }
+
+    /// Adds a method to the allowlist of idempotent methods. Calls to it are retried even if they
+    /// were made through `ClientHandle::call_method`.
+    pub fn idempotent_method(mut self, method: impl Into<String>) -> Self {
+        self.idempotent_methods.insert(method.into());
+        self
+    }
+
+    /// Returns true if the given method is on the allowlist of idempotent methods.
+    pub fn is_idempotent(&self, method: &str) -> bool {
+        self.idempotent_methods.contains(method)
+    }
+
+    /// Returns how long to wait before retrying a call that failed on the given attempt, counting
+    /// from zero, or `None` if the call should not be retried.
+    fn retry_delay(&self, attempt: u32, error: &Error) -> Option<Duration> {
+        if attempt + 1 >= self.max_attempts || !(self.retryable)(error) {
+            return None;
+        }
+        let mut backoff = match self.backoff.lock() {
+            Ok(backoff) => backoff,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        backoff.delay(attempt)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("idempotent_methods", &self.idempotent_methods)
+            .finish()
+    }
+}
+
+/// Sends a method call, sending it again according to the retry policy for as long as it fails.
+pub(crate) fn call_with_retries(
+    client: ClientHandle,
+    method: String,
+    params: Option<Params>,
+    timeout: Option<Duration>,
+    policy: Arc<RetryPolicy>,
+) -> impl Future<Item = JsonValue, Error = Error> {
+    future::loop_fn(0, move |attempt| {
+        let policy = policy.clone();
+        let method = method.clone();
+
+        client
+            .send_rpc_call(method.clone(), params.clone(), timeout)
+            .then(move |result| {
+                let error = match result {
+                    Ok(result) => return Either::B(future::ok(Loop::Break(result))),
+                    Err(error) => error,
+                };
+                match policy.retry_delay(attempt, &error) {
+                    Some(delay) => {
+                        debug!(
+                            "Call to {} failed, retrying in {:?}: {}",
+                            method, delay, error
+                        );
+                        Either::A(Delay::new(Instant::now() + delay).then(
+                            move |result| match result {
+                                Ok(()) => Ok(Loop::Continue(attempt + 1)),
+                                Err(e) => {
+                                    error!("Unable to wait before retrying a call: {}", e);
+                                    Err(error)
+                                }
+                            },
+                        ))
+                    }
+                    None => Either::B(future::err(error)),
+                }
+            })
+    })
+}
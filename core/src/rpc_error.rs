@@ -0,0 +1,254 @@
+use super::{Error, ErrorKind, Result, ResultExt};
+
+use serde;
+use serde_json;
+
+use std::any::Any;
+use std::fmt;
+
+pub use jsonrpc_core::types::{Error as RpcError, ErrorCode};
+
+
+/// The lowest error code of the range reserved for implementation-defined server errors.
+pub const SERVER_ERROR_RANGE_START: i64 = -32099;
+/// The highest error code of the range reserved for implementation-defined server errors.
+pub const SERVER_ERROR_RANGE_END: i64 = -32000;
+
+/// Helpers for inspecting the JSON-RPC 2.0 error objects returned by servers.
+pub trait RpcErrorExt {
+    /// Deserializes the `data` member of the error into the given type. Returns `None` if the
+    /// error does not carry any data.
+    fn data_as<T: serde::de::DeserializeOwned>(&self) -> Result<Option<T>>;
+
+    /// Returns true if the server could not parse the request as JSON.
+    fn is_parse_error(&self) -> bool;
+
+    /// Returns true if the server did not consider the request a valid request object.
+    fn is_invalid_request(&self) -> bool;
+
+    /// Returns true if the called method does not exist on the server.
+    fn is_method_not_found(&self) -> bool;
+
+    /// Returns true if the server rejected the parameters of the call.
+    fn is_invalid_params(&self) -> bool;
+
+    /// Returns true if the server failed with an internal JSON-RPC error.
+    fn is_internal_error(&self) -> bool;
+
+    /// Returns true if the error code is in the range from -32099 to -32000, reserved for
+    /// implementation-defined server errors.
+    fn is_server_error_range(&self) -> bool;
+
+    /// Returns true if sending the same request again might succeed. Only internal errors are
+    /// considered retryable, the other standard errors will fail the same way every time and the
+    /// meaning of server defined codes is not known.
+    fn is_retryable(&self) -> bool;
+}
+
+impl RpcErrorExt for RpcError {
+    fn data_as<T: serde::de::DeserializeOwned>(&self) -> Result<Option<T>> {
+        match self.data {
+            Some(ref data) => serde_json::from_value(data.clone())
+                .map(Some)
+                .chain_err(|| ErrorKind::DeserializeError),
+            None => Ok(None),
+        }
+    }
+
+    fn is_parse_error(&self) -> bool {
+        self.code == ErrorCode::ParseError
+    }
+
+    fn is_invalid_request(&self) -> bool {
+        self.code == ErrorCode::InvalidRequest
+    }
+
+    fn is_method_not_found(&self) -> bool {
+        self.code == ErrorCode::MethodNotFound
+    }
+
+    fn is_invalid_params(&self) -> bool {
+        self.code == ErrorCode::InvalidParams
+    }
+
+    fn is_internal_error(&self) -> bool {
+        self.code == ErrorCode::InternalError
+    }
+
+    fn is_server_error_range(&self) -> bool {
+        (SERVER_ERROR_RANGE_START..=SERVER_ERROR_RANGE_END).contains(&self.code.code())
+    }
+
+    fn is_retryable(&self) -> bool {
+        self.is_internal_error()
+    }
+}
+
+impl Error {
+    /// Returns the JSON-RPC 2.0 error object the server replied with, if this error is one.
+    pub fn rpc_error(&self) -> Option<&RpcError> {
+        match *self.kind() {
+            ErrorKind::JsonRpcError(ref error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Deserializes the `data` member of the JSON-RPC 2.0 error the server replied with. Returns
+    /// `None` if this is not a JSON-RPC 2.0 error or if the error does not carry any data.
+    pub fn rpc_error_data<T: serde::de::DeserializeOwned>(&self) -> Result<Option<T>> {
+        match self.rpc_error() {
+            Some(error) => error.data_as(),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns true if the server replied that the called method does not exist.
+    pub fn is_method_not_found(&self) -> bool {
+        self.rpc_error()
+            .map_or(false, RpcErrorExt::is_method_not_found)
+    }
+
+    /// Returns true if the server replied that the parameters of the call are invalid.
+    pub fn is_invalid_params(&self) -> bool {
+        self.rpc_error()
+            .map_or(false, RpcErrorExt::is_invalid_params)
+    }
+
+    /// Returns true if the server replied with an implementation-defined server error.
+    pub fn is_server_error_range(&self) -> bool {
+        self.rpc_error()
+            .map_or(false, RpcErrorExt::is_server_error_range)
+    }
+
+    /// Returns true if sending the same request again might succeed. This is the case for
+    /// timeouts, calls pending on a lost connection, calls the transport failed to deliver, HTTP
+    /// requests answered with 502, 503 or 504 and retryable JSON-RPC 2.0 errors.
+    pub fn is_retryable(&self) -> bool {
+        match *self.kind() {
+            ErrorKind::Timeout | ErrorKind::ConnectionLost | ErrorKind::TransportError => true,
+            ErrorKind::HttpError(status) => status == 502 || status == 503 || status == 504,
+            ErrorKind::JsonRpcError(ref error) => error.is_retryable(),
+            _ => false,
+        }
+    }
+}
+
+/// Turns an error into a JSON-RPC 2.0 error object, as used when answering calls from the server.
+/// JSON-RPC 2.0 errors are passed on as they are, and other errors become internal errors with the
+/// error as message.
+impl From<Error> for RpcError {
+    fn from(error: Error) -> RpcError {
+        match *error.kind() {
+            ErrorKind::JsonRpcError(ref rpc_error) => rpc_error.clone(),
+            _ => RpcError {
+                code: ErrorCode::InternalError,
+                message: error.to_string(),
+                data: None,
+            },
+        }
+    }
+}
+
+/// Errors that handlers of calls from the server can fail with, see
+/// [`Handler::method`](../server/enum.Handler.html#method.method). JSON-RPC 2.0 errors are replied
+/// as they are, and any other error that can be displayed becomes an internal error with the error
+/// as message, unless it is an `Error` holding a JSON-RPC 2.0 error. The type parameter only tells
+/// the two conversions apart and is inferred.
+pub trait IntoRpcError<M> {
+    /// Turns the error into the JSON-RPC 2.0 error object replied to the server.
+    fn into_rpc_error(self) -> RpcError;
+}
+
+/// Selects the conversion of `IntoRpcError` passing JSON-RPC 2.0 errors on as they are.
+#[derive(Debug)]
+pub enum Passthrough {}
+
+/// Selects the conversion of `IntoRpcError` turning other errors into internal errors.
+#[derive(Debug)]
+pub enum Displayed {}
+
+impl IntoRpcError<Passthrough> for RpcError {
+    fn into_rpc_error(self) -> RpcError {
+        self
+    }
+}
+
+impl<E: fmt::Display + 'static> IntoRpcError<Displayed> for E {
+    fn into_rpc_error(self) -> RpcError {
+        let rpc_error = (&self as &dyn Any)
+            .downcast_ref::<Error>()
+            .and_then(Error::rpc_error);
+        match rpc_error {
+            Some(rpc_error) => rpc_error.clone(),
+            None => RpcError {
+                code: ErrorCode::InternalError,
+                message: self.to_string(),
+                data: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc_error(code: i64) -> RpcError {
+        RpcError {
+            code: ErrorCode::from(code),
+            message: "test error".to_owned(),
+            data: None,
+        }
+    }
+
+    #[test]
+    fn classifies_standard_codes() {
+        assert!(rpc_error(-32601).is_method_not_found());
+        assert!(rpc_error(-32602).is_invalid_params());
+        assert!(rpc_error(-32603).is_retryable());
+        assert!(!rpc_error(-32602).is_retryable());
+        assert!(!rpc_error(-32099).is_retryable());
+        assert!(rpc_error(-32000).is_server_error_range());
+        assert!(rpc_error(-32099).is_server_error_range());
+        assert!(!rpc_error(-32100).is_server_error_range());
+        assert!(!rpc_error(-32603).is_server_error_range());
+    }
+
+    #[test]
+    fn deserializes_data() {
+        let mut error = rpc_error(-32001);
+        assert_eq!(error.data_as::<u64>().unwrap(), None);
+        error.data = Some(serde_json::Value::from(42));
+        assert_eq!(error.data_as::<u64>().unwrap(), Some(42));
+        assert!(error.data_as::<String>().is_err());
+
+        let error = Error::from(ErrorKind::JsonRpcError(error));
+        assert_eq!(error.rpc_error_data::<u64>().unwrap(), Some(42));
+        assert!(!Error::from(ErrorKind::Shutdown).is_retryable());
+        assert!(Error::from(ErrorKind::ConnectionLost).is_retryable());
+        assert!(Error::from(ErrorKind::TransportError).is_retryable());
+        assert!(Error::from(ErrorKind::HttpError(503)).is_retryable());
+        assert!(!Error::from(ErrorKind::HttpError(404)).is_retryable());
+    }
+
+    #[test]
+    fn converts_errors() {
+        let error = Error::from(ErrorKind::JsonRpcError(rpc_error(-32001)));
+        assert_eq!(RpcError::from(error), rpc_error(-32001));
+
+        let error = RpcError::from(Error::from(ErrorKind::Timeout));
+        assert!(error.is_internal_error());
+        assert_eq!(error.message, "The call did not receive a response in time");
+    }
+
+    #[test]
+    fn converts_handler_errors() {
+        assert_eq!(rpc_error(-32001).into_rpc_error(), rpc_error(-32001));
+        let error = Error::from(ErrorKind::JsonRpcError(rpc_error(-32001)));
+        assert_eq!(error.into_rpc_error(), rpc_error(-32001));
+
+        let error = "Out of cheese".into_rpc_error();
+        assert!(error.is_internal_error());
+        assert_eq!(error.message, "Out of cheese");
+    }
+}
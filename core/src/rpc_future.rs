@@ -0,0 +1,52 @@
+use super::Error;
+
+use futures::{Future, Poll};
+
+use std::fmt;
+
+
+/// The result of a method call, as returned by `ClientHandle` and by the methods generated by
+/// `jsonrpc_client!`.
+///
+/// `RpcFuture` is a futures 0.1 `Future`. With the `std-future` feature it is also a
+/// `std::future::Future`, so the call can be awaited directly in async code.
+#[must_use = "futures do nothing unless polled"]
+pub struct RpcFuture<T, E = Error> {
+    inner: Box<dyn Future<Item = T, Error = E> + Send>,
+}
+
+impl<T, E> RpcFuture<T, E> {
+    /// Wraps the given future.
+    pub fn new(inner: impl Future<Item = T, Error = E> + Send + 'static) -> Self {
+        RpcFuture {
+            inner: Box::new(inner),
+        }
+    }
+}
+
+impl<T, E> fmt::Debug for RpcFuture<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RpcFuture")
+    }
+}
+
+impl<T, E> Future for RpcFuture<T, E> {
+    type Item = T;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<T, E> {
+        self.inner.poll()
+    }
+}
+
+#[cfg(feature = "std-future")]
+impl<T, E> ::std::future::Future for RpcFuture<T, E> {
+    type Output = Result<T, E>;
+
+    fn poll(
+        self: ::std::pin::Pin<&mut Self>,
+        cx: &mut ::std::task::Context,
+    ) -> ::std::task::Poll<Result<T, E>> {
+        ::std_future::poll_01(&mut self.get_mut().inner, cx)
+    }
+}
@@ -1,17 +1,20 @@
 use super::{Error, ErrorKind, OutgoingMessage, Result};
-use id_generator::IdGenerator;
+use id_generator::NumericIdGenerator;
+use rpc_error::IntoRpcError;
 
 use futures::future::Either;
 use futures::{
     future, stream,
     sync::{mpsc, oneshot},
-    Async, Future, Sink, Stream,
+    Async, Future, IntoFuture, Sink, Stream,
 };
 pub use jsonrpc_core::types;
 use jsonrpc_core::types::{
-    Call, Error as RpcError, Failure, Id, MethodCall, Notification, Output, Request, Response,
-    Version,
+    Call, Error as RpcError, Failure, Id, MethodCall, Notification, Output, Params, Request,
+    Response, Success, Version,
 };
+use serde;
+use serde_json::{self, Value as JsonValue};
 
 use std::collections::{BTreeMap, HashMap};
 use std::error;
@@ -38,6 +41,105 @@ pub enum Handler {
 }
 
 impl Handler {
+    /// Creates a method handler calling `handler` with the parameters of the call deserialized
+    /// into `P`, and replying with the result serialized. Parameters that can't be deserialized
+    /// are replied to with an invalid params error. The handler can fail with a JSON-RPC 2.0
+    /// error object or any error that can be displayed, which is replied to as an internal error,
+    /// see [`IntoRpcError`](../rpc_error/trait.IntoRpcError.html).
+    pub fn method<P, R, F, T, M>(handler: F) -> Self
+    where
+        P: serde::de::DeserializeOwned,
+        R: serde::Serialize,
+        F: Fn(P) -> T + Send + 'static,
+        T: IntoFuture<Item = R>,
+        T::Future: Send + 'static,
+        T::Error: IntoRpcError<M>,
+    {
+        Handler::Method(Box::new(move |call: MethodCall| {
+            let id = call.id;
+            let result = match parse_params(call.params) {
+                Ok(params) => Either::A(handler(params).into_future().then(|result| {
+                    result
+                        .map_err(IntoRpcError::into_rpc_error)
+                        .and_then(|result| {
+                            serde_json::to_value(result).map_err(|_| RpcError::internal_error())
+                        })
+                })),
+                Err(error) => Either::B(future::err(error)),
+            };
+            Box::new(result.then(move |result| match result {
+                Ok(result) => Ok(success(id, result)),
+                Err(error) => Ok(failure(id, error)),
+            }))
+        }))
+    }
+
+    /// Creates a notification handler calling `handler` with the parameters of the notification
+    /// deserialized into `P`. Notifications can't be replied to, so invalid parameters and errors
+    /// of the handler are only logged.
+    pub fn notification<P, F, T, M>(handler: F) -> Self
+    where
+        P: serde::de::DeserializeOwned,
+        F: Fn(P) -> T + Send + 'static,
+        T: IntoFuture<Item = ()>,
+        T::Future: Send + 'static,
+        T::Error: IntoRpcError<M>,
+    {
+        Handler::Notification(Box::new(move |notification: Notification| {
+            let method = notification.method;
+            let result = match parse_params(notification.params) {
+                Ok(params) => Either::A(
+                    handler(params)
+                        .into_future()
+                        .then(|result| result.map_err(IntoRpcError::into_rpc_error)),
+                ),
+                Err(error) => Either::B(future::err(error)),
+            };
+            Box::new(result.then(move |result| {
+                if let Err(error) = result {
+                    error!(
+                        "Failed to handle notification {} - {}",
+                        method, error.message
+                    );
+                }
+                Ok(())
+            }))
+        }))
+    }
+
+    /// Creates a method handler passing the raw parameters of the call to `handler`, which takes
+    /// them apart with `take_named_param`. Primarily intended to be used from macro
+    /// `jsonrpc_server!`.
+    #[doc(hidden)]
+    pub fn named_method<R, F, T>(handler: F) -> Self
+    where
+        R: serde::Serialize + Send + 'static,
+        F: Fn(Params) -> result::Result<T, RpcError> + Send + 'static,
+        T: IntoFuture<Item = R, Error = RpcError>,
+        T::Future: Send + 'static,
+    {
+        Handler::method(move |params: Params| match handler(params) {
+            Ok(result) => Either::A(result.into_future()),
+            Err(error) => Either::B(future::err(error)),
+        })
+    }
+
+    /// Creates a notification handler passing the raw parameters of the notification to
+    /// `handler`, which takes them apart with `take_named_param`. Primarily intended to be used
+    /// from macro `jsonrpc_server!`.
+    #[doc(hidden)]
+    pub fn named_notification<F, T>(handler: F) -> Self
+    where
+        F: Fn(Params) -> result::Result<T, RpcError> + Send + 'static,
+        T: IntoFuture<Item = (), Error = RpcError>,
+        T::Future: Send + 'static,
+    {
+        Handler::notification(move |params: Params| match handler(params) {
+            Ok(result) => Either::A(result.into_future()),
+            Err(error) => Either::B(future::err(error)),
+        })
+    }
+
     fn description(&self) -> &'static str {
         match self {
             Handler::Method(_) => "method",
@@ -199,6 +301,56 @@ impl fmt::Debug for Handlers {
     }
 }
 
+/// Deserializes the parameters of a call. Calls without parameters might be sent with an empty
+/// array or object as well, so those are also accepted where no parameters are expected.
+fn parse_params<P: serde::de::DeserializeOwned>(
+    params: Option<Params>,
+) -> result::Result<P, RpcError> {
+    let params = params.unwrap_or(Params::None);
+    let empty = match params {
+        Params::Array(ref values) => values.is_empty(),
+        Params::Map(ref values) => values.is_empty(),
+        Params::None => false,
+    };
+    params.parse().or_else(|error| {
+        if empty {
+            Params::None.parse().map_err(|_| error)
+        } else {
+            Err(error)
+        }
+    })
+}
+
+/// Takes the parameter with the given name out of parameters passed by name, and deserializes it.
+/// Missing parameters are deserialized from `null`, so that optional arguments can be left out.
+/// Primarily intended to be used from macro `jsonrpc_server!`.
+#[doc(hidden)]
+pub fn take_named_param<T: serde::de::DeserializeOwned>(
+    params: &mut Params,
+    name: &str,
+) -> result::Result<T, RpcError> {
+    let value = match *params {
+        Params::Map(ref mut values) => values.remove(name),
+        Params::None => None,
+        Params::Array(_) => {
+            return Err(RpcError::invalid_params(
+                "Expected the parameters to be passed by name",
+            ))
+        }
+    };
+    serde_json::from_value(value.unwrap_or(JsonValue::Null)).map_err(|error| {
+        RpcError::invalid_params(format!("Invalid parameter `{}`: {}", name, error))
+    })
+}
+
+fn success(id: Id, result: JsonValue) -> Output {
+    Output::Success(Success {
+        jsonrpc: Some(Version::V2),
+        result,
+        id,
+    })
+}
+
 fn failure(id: Id, error: RpcError) -> Output {
     Output::Failure(Failure {
         jsonrpc: Some(Version::V2),
@@ -251,6 +403,41 @@ impl ServerHandle {
             .and_then(|r| future::result(r))
     }
 
+    /// Sets a handler for calls of the given method, with typed parameters and result. See
+    /// `Handler::method`.
+    pub fn add_method<P, R, F, T, M>(
+        &self,
+        name: String,
+        handler: F,
+    ) -> impl Future<Item = (), Error = HandlerSettingError>
+    where
+        P: serde::de::DeserializeOwned,
+        R: serde::Serialize,
+        F: Fn(P) -> T + Send + 'static,
+        T: IntoFuture<Item = R>,
+        T::Future: Send + 'static,
+        T::Error: IntoRpcError<M>,
+    {
+        self.add(name, Handler::method(handler))
+    }
+
+    /// Sets a handler for the given notification, with typed parameters. See
+    /// `Handler::notification`.
+    pub fn add_notification<P, F, T, M>(
+        &self,
+        name: String,
+        handler: F,
+    ) -> impl Future<Item = (), Error = HandlerSettingError>
+    where
+        P: serde::de::DeserializeOwned,
+        F: Fn(P) -> T + Send + 'static,
+        T: IntoFuture<Item = ()>,
+        T::Future: Send + 'static,
+        T::Error: IntoRpcError<M>,
+    {
+        self.add(name, Handler::notification(handler))
+    }
+
     /// Unsets a handler
     pub fn remove(&self, name: String) -> impl Future<Item = Handler, Error = HandlerError> {
         let (result_tx, result_rx) = oneshot::channel();
@@ -269,7 +456,7 @@ pub struct Server {
     handler_chan: Option<mpsc::Receiver<HandlerMsg>>,
     handler_map: Handlers,
     pending_futures: BTreeMap<u64, DrivableCall>,
-    id_generator: IdGenerator,
+    id_generator: NumericIdGenerator,
 }
 
 impl Server {
@@ -280,13 +467,23 @@ impl Server {
             Self {
                 handler_map: Handlers::new(),
                 pending_futures: BTreeMap::new(),
-                id_generator: IdGenerator::new(),
+                id_generator: NumericIdGenerator::new(),
                 handler_chan: Some(rx),
             },
             ServerHandle { tx },
         )
     }
 
+    /// Sets a new handler before the server is handed to a client. Once the server is running,
+    /// handlers are set through its `ServerHandle` instead.
+    pub fn add(
+        &mut self,
+        name: String,
+        handler: Handler,
+    ) -> result::Result<(), HandlerSettingError> {
+        self.handler_map.add(name, handler)
+    }
+
     fn push_future(&mut self, driveable_future: DrivableCall) {
         self.pending_futures
             .insert(self.id_generator.next_int(), driveable_future);
@@ -0,0 +1,149 @@
+use super::{server, CallFailures, Client, ClientHandle};
+
+use futures;
+use futures_util::compat::{Compat, Compat01As03, CompatSink};
+use futures_util::{FutureExt, Sink, Stream};
+
+use std::error;
+use std::task::{Context, Poll};
+
+
+/// A transport built on the `Sink` and `Stream` traits of futures 0.3. The counterpart of
+/// [`Transport`](../trait.Transport.html) for transports written against `std::future`.
+///
+/// Such a transport is used by a client through [`TransportCompat`](struct.TransportCompat.html),
+/// which [`into_client`](#method.into_client) wraps it in.
+pub trait Transport: Sized + Send {
+    /// A transport specific error
+    type Error: error::Error + Send + 'static;
+    /// A stream of strings, each of which represent a single JSON value that is either an array or
+    /// an object used to receive messages from a JSON-RPC server.
+    type Stream: Stream<Item = Result<String, Self::Error>> + Unpin + Send;
+    /// A sink of strings, each of which represent a single JSON value that is either an array or an
+    /// object used to send messages to a JSON-RPC server.
+    type Sink: Sink<String, Error = Self::Error> + Unpin + Send;
+
+    /// Transforms the transport implementation into a sink and a stream.
+    fn io_pair(self) -> (Self::Sink, Self::Stream);
+
+    /// Gives the transport the means to fail single method calls, see
+    /// [`Transport::set_call_failures`](../trait.Transport.html#method.set_call_failures).
+    fn set_call_failures(&mut self, _call_failures: CallFailures) {}
+
+    /// Creates a Client and a ClientHandle from a transport implementation.
+    fn into_client(self) -> (Client<TransportCompat<Self>, server::Server>, ClientHandle) {
+        Client::new(TransportCompat(self))
+    }
+}
+
+/// The counterpart of [`DuplexTransport`](../trait.DuplexTransport.html) for transports written
+/// against `std::future`.
+pub trait DuplexTransport: Transport {
+    /// Constructs a new client with the provided server handler.
+    fn with_server<S: server::ServerHandler>(
+        self,
+        s: S,
+    ) -> (Client<TransportCompat<Self>, S>, ClientHandle) {
+        Client::with_server(TransportCompat(self), s)
+    }
+}
+
+/// Adapts a futures 0.3 based [`Transport`](trait.Transport.html) to the
+/// [`Transport`](../trait.Transport.html) trait of this crate.
+#[derive(Debug)]
+pub struct TransportCompat<T>(pub T);
+
+impl<T: Transport> ::Transport for TransportCompat<T> {
+    type Error = T::Error;
+    type Stream = Compat<T::Stream>;
+    type Sink = CompatSink<T::Sink, String>;
+
+    fn io_pair(self) -> (Self::Sink, Self::Stream) {
+        let (sink, stream) = self.0.io_pair();
+        (CompatSink::new(sink), Compat::new(stream))
+    }
+
+    fn set_call_failures(&mut self, call_failures: CallFailures) {
+        self.0.set_call_failures(call_failures)
+    }
+}
+
+impl<T: DuplexTransport> ::DuplexTransport for TransportCompat<T> {}
+
+impl<T: ::Transport, S: server::ServerHandler> Client<T, S> {
+    /// Turns the client into a `std::future::Future`, to be spawned on an async executor. Timeouts
+    /// and retries rely on `tokio-timer` 0.2, so they only work when the executor provides a timer
+    /// for it.
+    pub fn into_std_future(self) -> Compat01As03<Self> {
+        Compat01As03::new(self)
+    }
+}
+
+/// Polls a futures 0.1 future from a `std::future::Future`.
+pub(crate) fn poll_01<F: futures::Future>(
+    future: &mut F,
+    cx: &mut Context,
+) -> Poll<Result<F::Item, F::Error>> {
+    Compat01As03::new(future).poll_unpin(cx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::sync::mpsc;
+    use futures::{Future, Sink as Sink01, Stream as Stream01};
+    use futures_util::compat::Compat01As03Sink;
+    use jsonrpc_core::types::{MethodCall, Output, Params, Success, Version};
+    use serde_json;
+    use std::io;
+    use std::thread;
+    use RpcFuture;
+
+    type BoxStream01 = Box<dyn Stream01<Item = String, Error = io::Error> + Send>;
+    type BoxSink01 = Box<dyn Sink01<SinkItem = String, SinkError = io::Error> + Send>;
+
+    /// A futures 0.3 transport to a server echoing the first parameter of every call.
+    struct EchoTransport;
+
+    impl Transport for EchoTransport {
+        type Error = io::Error;
+        type Stream = Compat01As03<BoxStream01>;
+        type Sink = Compat01As03Sink<BoxSink01, String>;
+
+        fn io_pair(self) -> (Self::Sink, Self::Stream) {
+            let (requests_tx, requests_rx) = mpsc::unbounded::<String>();
+            let responses = requests_rx
+                .map(|request| {
+                    let call: MethodCall = serde_json::from_str(&request).unwrap();
+                    let result = match call.params {
+                        Some(Params::Array(mut params)) => params.remove(0),
+                        params => panic!("Unexpected params: {:?}", params),
+                    };
+                    let output = Output::Success(Success {
+                        jsonrpc: Some(Version::V2),
+                        result,
+                        id: call.id,
+                    });
+                    serde_json::to_string(&output).unwrap()
+                })
+                .map_err(|()| unreachable!());
+            let requests_tx = requests_tx
+                .sink_map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Server is gone"));
+            (
+                Compat01As03Sink::new(Box::new(requests_tx)),
+                Compat01As03::new(Box::new(responses)),
+            )
+        }
+    }
+
+    #[test]
+    fn calls_through_std_transport() {
+        let (client, client_handle) = EchoTransport.into_client();
+        let client = thread::spawn(move || Compat::new(client.into_std_future()).wait());
+
+        let call: RpcFuture<String> = client_handle.call_method("echo", &["abc"]);
+        assert_eq!("abc", Compat::new(call).wait().unwrap());
+        drop(client_handle);
+        client.join().unwrap().unwrap();
+    }
+}
@@ -89,7 +89,8 @@ use futures::sync::{mpsc, oneshot};
 use futures::{Async, Future, Poll, Sink, Stream};
 pub use hyper::header;
 use hyper::{Client, Request, StatusCode, Uri};
-use jsonrpc_client_core::Transport;
+use jsonrpc_client_core::call_failures::CallFailures;
+use jsonrpc_client_core::{ErrorKind as CoreErrorKind, Transport};
 use std::str::FromStr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
@@ -190,6 +191,7 @@ impl HttpTransport {
             uri,
             id: self.id.clone(),
             headers: header::Headers::new(),
+            call_failures: None,
         })
     }
 }
@@ -384,12 +386,19 @@ fn create_request_processing_future<CC: hyper::client::Connect>(
 /// A handle to a [`HttpTransport`](struct.HttpTransport.html). This implements
 /// `jsonrpc_client_core::Transport` and can be used as the transport for a RPC client generated
 /// by the `jsonrpc_client!` macro.
+///
+/// When a client uses the handle, a request that fails on its own only fails the calls it carries,
+/// and the client keeps running. Calls answered with an HTTP status other than 200 OK fail with
+/// `ErrorKind::HttpError` of `jsonrpc_client_core`, calls whose request timed out with
+/// `ErrorKind::Timeout` and calls whose request could not be delivered with
+/// `ErrorKind::TransportError`.
 #[derive(Debug, Clone)]
 pub struct HttpHandle {
     request_tx: CoreSender,
     uri: Uri,
     id: Arc<AtomicUsize>,
     headers: header::Headers,
+    call_failures: Option<CallFailures>,
 }
 
 impl HttpHandle {
@@ -451,14 +460,48 @@ impl Transport for HttpHandle {
 
     fn io_pair(self) -> (Self::Sink, Self::Stream) {
         let (tx, rx) = mpsc::channel(0);
+        let call_failures = self.call_failures.clone();
         let sink = tx
             .sink_map_err(|_| Error::from(ErrorKind::TokioCoreError("Not listening for requests")))
-            .with(move |json_string: String| self.send_fut(json_string.into_bytes()));
+            .with(move |json_string: String| {
+                let call_failures = call_failures.clone();
+                self.send_fut(json_string.clone().into_bytes())
+                    .map(Some)
+                    .or_else(move |e| match call_failures {
+                        Some(ref call_failures) if fail_calls(call_failures, &json_string, &e) => {
+                            Ok(None)
+                        }
+                        _ => Err(e),
+                    })
+            });
         let stream = rx
+            .filter_map(|bytes| bytes)
             .map_err(|_| Error::from(ErrorKind::TokioCoreError("Sender closed")))
             .and_then(|bytes| String::from_utf8(bytes).chain_err(|| ErrorKind::ParseBodyError));
         (Box::new(sink), Box::new(stream))
     }
+
+    fn set_call_failures(&mut self, call_failures: CallFailures) {
+        self.call_failures = Some(call_failures);
+    }
+}
+
+/// Fails the calls in the payload of a request that failed on its own, without the transport as a
+/// whole failing. Returns false if the error is not specific to the request.
+fn fail_calls(call_failures: &CallFailures, payload: &str, error: &Error) -> bool {
+    match *error.kind() {
+        ErrorKind::HttpError(ref status) => {
+            let status = status.as_u16();
+            call_failures.fail_payload(payload, || CoreErrorKind::HttpError(status));
+        }
+        ErrorKind::RequestTimeout => call_failures.fail_payload(payload, || CoreErrorKind::Timeout),
+        ErrorKind::Hyper(_) => {
+            call_failures.fail_payload(payload, || CoreErrorKind::TransportError)
+        }
+        _ => return false,
+    }
+    warn!("Failing the calls of a request that failed: {}", error);
+    true
 }
 
 #[cfg(test)]
@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use futures::future::{self, Empty};
+use futures::{Future, Stream};
 use jsonrpc_core::{Error, IoHandler};
 use jsonrpc_http_server::hyper::server::{Request, Response, Service};
 use jsonrpc_http_server::{self, hyper, ServerBuilder};
@@ -18,6 +21,9 @@ build_rpc_trait! {
 
         #[rpc(name = "sleep")]
         fn sleep(&self, u64) -> Result<(), Error>;
+
+        #[rpc(name = "flaky_to_upper")]
+        fn flaky_to_upper(&self, String) -> Result<String, Error>;
     }
 }
 
@@ -26,21 +32,32 @@ jsonrpc_client!(pub struct MockRpcClient {
     pub fn to_upper(&mut self, string: &str) -> Future<String>;
     pub fn slow_to_upper(&mut self, string: &str, time: u64) -> Future<String>;
     pub fn sleep(&mut self, time: u64) -> Future<()>;
+    #[idempotent]
+    pub fn flaky_to_upper(&mut self, string: &str) -> Future<String>;
 });
 
 
 /// Simple struct that will implement the RPC API defined at the top of this file.
-pub struct MockRpcServer;
+pub struct MockRpcServer {
+    /// Number of calls to `flaky_to_upper`, every other one of them fails.
+    flaky_calls: AtomicUsize,
+}
 
 impl MockRpcServer {
     pub fn spawn() -> jsonrpc_http_server::Server {
-        let mut io = IoHandler::new();
-        io.extend_with(MockRpcServer.to_delegate());
-
-        ServerBuilder::new(io)
+        ServerBuilder::new(Self::io_handler())
             .start_http(&"127.0.0.1:0".parse().unwrap())
             .expect("failed to spawn server")
     }
+
+    fn io_handler() -> IoHandler {
+        let server = MockRpcServer {
+            flaky_calls: AtomicUsize::new(0),
+        };
+        let mut io = IoHandler::new();
+        io.extend_with(server.to_delegate());
+        io
+    }
 }
 
 impl MockRpcServerApi for MockRpcServer {
@@ -58,6 +75,14 @@ impl MockRpcServerApi for MockRpcServer {
         ::std::thread::sleep(Duration::from_secs(time));
         Ok(())
     }
+
+    fn flaky_to_upper(&self, s: String) -> Result<String, Error> {
+        if self.flaky_calls.fetch_add(1, Ordering::SeqCst) % 2 == 0 {
+            Err(Error::internal_error())
+        } else {
+            Ok(s.to_uppercase())
+        }
+    }
 }
 
 pub struct UnresponsiveService;
@@ -72,3 +97,29 @@ impl Service for UnresponsiveService {
         future::empty()
     }
 }
+
+/// Answers the first `unavailable` requests it gets with 503 Service Unavailable, and the
+/// following ones like a `MockRpcServer`.
+pub struct UnavailableService {
+    pub requests: Arc<AtomicUsize>,
+    pub unavailable: usize,
+}
+
+impl Service for UnavailableService {
+    type Request = Request;
+    type Response = Response;
+    type Error = hyper::Error;
+    type Future = Box<dyn Future<Item = Self::Response, Error = Self::Error>>;
+
+    fn call(&self, request: Self::Request) -> Self::Future {
+        if self.requests.fetch_add(1, Ordering::SeqCst) < self.unavailable {
+            let response = Response::new().with_status(hyper::StatusCode::ServiceUnavailable);
+            return Box::new(future::ok(response));
+        }
+        Box::new(request.body().concat2().map(|body| {
+            let request = String::from_utf8_lossy(&body);
+            let response = MockRpcServer::io_handler().handle_request_sync(&request);
+            Response::new().with_body(response.unwrap_or_default())
+        }))
+    }
+}
@@ -22,13 +22,33 @@ mod common;
 
 use futures::future::Either;
 use futures::Future;
-use jsonrpc_client_core::Transport;
+use jsonrpc_client_core::backoff::FixedBackoff;
+use jsonrpc_client_core::blocking::BlockingClient;
+use jsonrpc_client_core::circuit_breaker::{CircuitBreaker, CircuitState};
+use jsonrpc_client_core::retry::RetryPolicy;
+use jsonrpc_client_core::{Error, ErrorKind, Transport};
 use jsonrpc_client_http::HttpTransport;
+use jsonrpc_core::ErrorCode;
+use jsonrpc_http_server::hyper::server::Http;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 use std::time::Duration;
 use tokio_core::reactor::{Core, Timeout};
 
 // Use a simple RPC API for testing purposes.
-use common::{MockRpcClient, MockRpcServer};
+use common::{MockRpcClient, MockRpcServer, UnavailableService};
+
+// A client for the same API, failing with an application error enum.
+jsonrpc_client!(pub struct ErrorMappingClient {
+    pub enum MockRpcError {
+        /// The method does not exist on the server.
+        NoSuchMethod = -32601,
+    }
+
+    pub fn no_such_method(&mut self) -> Future<()>;
+    pub fn to_upper(&mut self, number: u64) -> Future<String>;
+});
 
 
 #[test]
@@ -93,3 +113,206 @@ fn dropped_rpc_request_should_not_crash_transport() {
         _ => panic!("Sleep did not return as it should"),
     }
 }
+
+#[test]
+fn localhost_batch() {
+    let server = MockRpcServer::spawn();
+    let uri = format!("http://{}", server.address());
+
+    let mut core = Core::new().unwrap();
+    let transport = HttpTransport::new()
+        .shared(&core.handle())
+        .unwrap()
+        .handle(&uri)
+        .unwrap();
+    let (json_client, client_handle) = transport.into_client();
+    core.handle().spawn(json_client.map_err(|_| ()));
+
+    let mut batch = client_handle.batch();
+    let rpc_future1 = batch.call_method::<String>("to_upper", &["foo"]);
+    let rpc_future2 = batch.call_method::<String>("no_such_method", &["bar"]);
+    let rpc_future3 = batch.call_method::<String>("to_upper", &["baz"]);
+    let notification = batch.send_notification("sleep", &[0]);
+    assert_eq!(batch.len(), 4);
+    core.run(batch.send()).unwrap();
+    core.run(notification).unwrap();
+
+    assert_eq!("FOO", core.run(rpc_future1).unwrap());
+    match core.run(rpc_future2) {
+        Err(Error(ErrorKind::JsonRpcError(ref error), _)) => {
+            assert_eq!(error.code, ErrorCode::MethodNotFound)
+        }
+        result => panic!("Expected a method not found error, got {:?}", result),
+    }
+    assert_eq!("BAZ", core.run(rpc_future3).unwrap());
+}
+
+#[test]
+fn localhost_application_error() {
+    let server = MockRpcServer::spawn();
+    let uri = format!("http://{}", server.address());
+
+    let mut core = Core::new().unwrap();
+    let transport = HttpTransport::new()
+        .shared(&core.handle())
+        .unwrap()
+        .handle(&uri)
+        .unwrap();
+    let (json_client, client_handle) = transport.into_client();
+    core.handle().spawn(json_client.map_err(|_| ()));
+    let mut client = ErrorMappingClient::new(client_handle);
+
+    match core.run(client.no_such_method()) {
+        Err(MockRpcError::NoSuchMethod(ref error)) => assert!(error.code.code() == -32601),
+        result => panic!("Expected a NoSuchMethod error, got {:?}", result),
+    }
+    match core.run(client.to_upper(5)) {
+        Err(MockRpcError::Other(ref error)) => {
+            assert!(error.is_invalid_params());
+            assert!(!error.is_retryable());
+        }
+        result => panic!("Expected an invalid params error, got {:?}", result),
+    }
+}
+
+#[test]
+fn localhost_retry_idempotent_method() {
+    let server = MockRpcServer::spawn();
+    let uri = format!("http://{}", server.address());
+
+    let mut core = Core::new().unwrap();
+    let transport = HttpTransport::new()
+        .shared(&core.handle())
+        .unwrap()
+        .handle(&uri)
+        .unwrap();
+    let (json_client, client_handle) = transport.into_client();
+    core.handle().spawn(json_client.map_err(|_| ()));
+    let retry_policy = RetryPolicy::new()
+        .max_attempts(2)
+        .backoff(FixedBackoff::new(Duration::from_millis(10)));
+    let mut client = MockRpcClient::new(client_handle.retry_policy(retry_policy));
+
+    // Every other call to the method fails with an internal error, so every call should succeed on
+    // either the first or the second attempt.
+    for _ in 0..4 {
+        assert_eq!("FOOBAR", core.run(client.flaky_to_upper("foobar")).unwrap());
+    }
+}
+
+#[test]
+fn localhost_retry_unavailable_server() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server_requests = requests.clone();
+    let (address_tx, address_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let address = "127.0.0.1:0".parse().unwrap();
+        let server = Http::new()
+            .bind(&address, move || {
+                Ok(UnavailableService {
+                    requests: server_requests.clone(),
+                    unavailable: 2,
+                })
+            }).unwrap();
+        address_tx.send(server.local_addr().unwrap()).unwrap();
+        server.run().unwrap();
+    });
+    let uri = format!("http://{}", address_rx.recv().unwrap());
+
+    let mut core = Core::new().unwrap();
+    let transport = HttpTransport::new()
+        .shared(&core.handle())
+        .unwrap()
+        .handle(&uri)
+        .unwrap();
+    let (json_client, client_handle) = transport.into_client();
+    core.handle().spawn(json_client.map_err(|e| panic!("Client failed: {}", e)));
+
+    // Without a retry policy, the call fails with the status of the response.
+    let call = client_handle.call_method::<String>("to_upper", &("foobar",));
+    match core.run(call) {
+        Err(Error(ErrorKind::HttpError(503), _)) => (),
+        result => panic!("Expected a 503 error, got {:?}", result),
+    }
+
+    // The server is still unavailable for the first attempt, and the retry succeeds.
+    let retry_policy = RetryPolicy::new()
+        .max_attempts(2)
+        .backoff(FixedBackoff::new(Duration::from_millis(10)));
+    let call = client_handle
+        .retry_policy(retry_policy)
+        .call_idempotent_method::<String>("to_upper", &("foobar",));
+    assert_eq!("FOOBAR", core.run(call).unwrap());
+    assert_eq!(3, requests.load(Ordering::SeqCst));
+}
+
+#[test]
+fn localhost_circuit_breaker() {
+    let server = MockRpcServer::spawn();
+    let uri = format!("http://{}", server.address());
+
+    let mut core = Core::new().unwrap();
+    let transport = HttpTransport::new()
+        .shared(&core.handle())
+        .unwrap()
+        .handle(&uri)
+        .unwrap();
+    let (json_client, client_handle) = transport.into_client();
+    core.handle().spawn(json_client.map_err(|_| ()));
+
+    let (state_tx, state_rx) = mpsc::channel();
+    let state_tx = Mutex::new(state_tx);
+    let circuit_breaker = CircuitBreaker::new()
+        .window_size(2)
+        .minimum_calls(2)
+        .failure_if(|error| error.is_method_not_found())
+        .on_state_change(move |old_state, new_state| {
+            state_tx
+                .lock()
+                .unwrap()
+                .send((old_state, new_state))
+                .unwrap();
+        });
+    let mut client = ErrorMappingClient::new(client_handle.circuit_breaker(circuit_breaker));
+
+    for _ in 0..2 {
+        match core.run(client.no_such_method()) {
+            Err(MockRpcError::NoSuchMethod(_)) => (),
+            result => panic!("Expected a NoSuchMethod error, got {:?}", result),
+        }
+    }
+    assert_eq!(
+        state_rx.try_recv(),
+        Ok((CircuitState::Closed, CircuitState::Open))
+    );
+    match core.run(client.to_upper(5)) {
+        Err(MockRpcError::Other(Error(ErrorKind::CircuitOpen, _))) => (),
+        result => panic!("Expected the circuit to be open, got {:?}", result),
+    }
+}
+
+#[test]
+fn localhost_blocking_client() {
+    let server = MockRpcServer::spawn();
+    let uri = format!("http://{}", server.address());
+
+    let transport = HttpTransport::new()
+        .standalone()
+        .unwrap()
+        .handle(&uri)
+        .unwrap();
+    let blocking_client = BlockingClient::new(transport).unwrap();
+
+    let result: String = blocking_client.call("to_upper", &["foobar"]).unwrap();
+    assert_eq!("FOOBAR", result);
+    assert!(blocking_client.call::<()>("no_such_method", &()).is_err());
+
+    let mut client = MockRpcClient::new(blocking_client.handle().clone());
+    assert_eq!("BAZ", client.to_upper("baz").wait().unwrap());
+
+    blocking_client.shutdown().unwrap();
+    match client.to_upper("baz").wait() {
+        Err(Error(ErrorKind::Shutdown, _)) => (),
+        result => panic!("Expected the client to be shut down, got {:?}", result),
+    }
+}
@@ -25,7 +25,7 @@ use std::sync::mpsc;
 use std::time::Duration;
 
 use futures::future::{Either, Future};
-use jsonrpc_client_core::Transport;
+use jsonrpc_client_core::{Error as CoreError, ErrorKind as CoreErrorKind, Transport};
 use jsonrpc_client_http::{ErrorKind, HttpTransport};
 use jsonrpc_http_server::hyper::server::Http;
 use tokio_core::reactor::{Core, Timeout};
@@ -126,3 +126,54 @@ fn timeout_error() {
         },
     }
 }
+
+#[test]
+fn call_with_timeout_should_time_out() {
+    let server = MockRpcServer::spawn();
+    let uri = format!("http://{}", server.address());
+
+    let mut core = Core::new().unwrap();
+    let transport = HttpTransport::new()
+        .shared(&core.handle())
+        .unwrap()
+        .handle(&uri)
+        .unwrap();
+    let (json_client, client_handle) = transport.into_client();
+    core.handle().spawn(json_client.map_err(|_| ()));
+
+    let rpc_future = client_handle.call_method_with_timeout::<String>(
+        "slow_to_upper",
+        &("HARD string TAKES too LONG", 1_000),
+        Duration::from_millis(50),
+    );
+    match core.run(rpc_future) {
+        Err(CoreError(CoreErrorKind::Timeout, _)) => (),
+        result => panic!("Expected the call to time out, got {:?}", result),
+    }
+
+    // The client should still be usable after a call timed out.
+    let mut client = MockRpcClient::new(client_handle);
+    assert_eq!("FAST", core.run(client.to_upper("fast")).unwrap());
+}
+
+#[test]
+fn default_timeout_should_time_out() {
+    let server = MockRpcServer::spawn();
+    let uri = format!("http://{}", server.address());
+
+    let mut core = Core::new().unwrap();
+    let transport = HttpTransport::new()
+        .shared(&core.handle())
+        .unwrap()
+        .handle(&uri)
+        .unwrap();
+    let (json_client, client_handle) = transport.into_client();
+    let json_client = json_client.default_timeout(Duration::from_millis(50));
+    core.handle().spawn(json_client.map_err(|_| ()));
+    let mut client = MockRpcClient::new(client_handle);
+
+    match core.run(client.slow_to_upper("HARD string TAKES too LONG", 1_000)) {
+        Err(CoreError(CoreErrorKind::Timeout, _)) => (),
+        result => panic!("Expected the call to time out, got {:?}", result),
+    }
+}
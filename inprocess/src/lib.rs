@@ -0,0 +1,240 @@
+// Copyright 2018 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An in-process transport for the JSON-RPC 2.0 clients generated by
+//! [`jsonrpc-client-core`](../jsonrpc_client_core/index.html).
+//!
+//! [`InProcessTransport`](struct.InProcessTransport.html) hands every request of the client
+//! directly to a `jsonrpc_core` `IoHandler` living in the same process, and streams the responses
+//! back, so a server and its clients can be embedded in the same binary without a socket between
+//! them.
+//!
+//! # Pushing messages to the client
+//!
+//! The transport is a duplex transport. Anything sent on the sender returned by
+//! [`InProcessTransport::sender`](struct.InProcessTransport.html#method.sender) is delivered to
+//! the client, and handled by the `ServerHandler` the client was created with. The sender is also
+//! passed to the function creating the request metadata in
+//! [`InProcessTransport::with_metadata`](struct.InProcessTransport.html#method.with_metadata), so
+//! it can for example be wrapped in a `jsonrpc_pubsub::Session`.
+//!
+//! Responses the client sends to method calls pushed through the sender are delivered on the
+//! receiver returned by
+//! [`InProcessTransport::responses`](struct.InProcessTransport.html#method.responses).
+//!
+//! ```rust,ignore
+//! let mut io = IoHandler::new();
+//! io.add_method("to_upper", |params: Params| { /* ... */ });
+//! let (client, client_handle) = InProcessTransport::new(io).into_client();
+//! ```
+//!
+//! # Skipping serialization
+//!
+//! A transport passes every message as a string. Clients that only talk to the handler can skip
+//! serializing and parsing the messages with
+//! [`InProcessTransport::into_typed_client`](struct.InProcessTransport.html#method.into_typed_client),
+//! which hands the requests to the handler as `jsonrpc_core` values instead.
+
+#![deny(missing_docs)]
+
+extern crate futures;
+extern crate jsonrpc_client_core;
+extern crate jsonrpc_core;
+#[macro_use]
+extern crate log;
+extern crate serde_json;
+extern crate tokio_timer;
+
+use futures::stream::FuturesUnordered;
+use futures::sync::mpsc;
+use futures::{Async, AsyncSink, Future, Poll, Sink, StartSend, Stream};
+use jsonrpc_client_core::{ClientHandle, DuplexTransport, Transport};
+use jsonrpc_core::{IoHandler, MetaIoHandler, Metadata, Middleware, NoopMiddleware, Response};
+
+use std::io;
+use std::sync::Arc;
+
+mod typed;
+pub use typed::InProcessClient;
+
+
+/// The number of messages pushed to the client that are buffered before senders have to wait.
+const PUSH_BUFFER_SIZE: usize = 16;
+
+type PendingResponse = Box<dyn Future<Item = Option<String>, Error = ()> + Send>;
+
+/// A transport calling a `jsonrpc_core` handler in the same process.
+///
+/// Requests are handled concurrently, and their responses are sent back to the client in the order
+/// they complete. Responses the client sends to method calls pushed to it through the
+/// [`sender`](#method.sender) are delivered on the receiver returned by
+/// [`responses`](#method.responses), and dropped if there is none.
+pub struct InProcessTransport<M: Metadata = (), S: Middleware<M> = NoopMiddleware> {
+    io: Arc<MetaIoHandler<M, S>>,
+    metadata: M,
+    push_tx: mpsc::Sender<String>,
+    push_rx: mpsc::Receiver<String>,
+    pushed_responses_tx: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl InProcessTransport {
+    /// Creates a transport calling the given handler.
+    pub fn new(io: IoHandler) -> Self {
+        Self::with_metadata(MetaIoHandler::from(io), |_| ())
+    }
+}
+
+impl<M: Metadata, S: Middleware<M>> InProcessTransport<M, S> {
+    /// Creates a transport calling the given handler with metadata. The metadata is created once
+    /// by `metadata`, from a sender pushing messages to the client, and a clone of it is passed
+    /// along with every request.
+    pub fn with_metadata<F>(io: impl Into<Arc<MetaIoHandler<M, S>>>, metadata: F) -> Self
+    where
+        F: FnOnce(mpsc::Sender<String>) -> M,
+    {
+        let (push_tx, push_rx) = mpsc::channel(PUSH_BUFFER_SIZE);
+        InProcessTransport {
+            io: io.into(),
+            metadata: metadata(push_tx.clone()),
+            push_tx,
+            push_rx,
+            pushed_responses_tx: None,
+        }
+    }
+
+    /// Returns a sender for pushing notifications and method calls to the client, as if the
+    /// handler sent them.
+    pub fn sender(&self) -> mpsc::Sender<String> {
+        self.push_tx.clone()
+    }
+
+    /// Returns a receiver of the responses the client sends to method calls pushed through the
+    /// [`sender`](#method.sender). A receiver returned earlier stops receiving responses.
+    pub fn responses(&mut self) -> mpsc::UnboundedReceiver<String> {
+        let (pushed_responses_tx, pushed_responses_rx) = mpsc::unbounded();
+        self.pushed_responses_tx = Some(pushed_responses_tx);
+        pushed_responses_rx
+    }
+
+    /// Creates a client passing requests and responses to the handler as `jsonrpc_core` values,
+    /// without serializing them to strings. See [`InProcessClient`](struct.InProcessClient.html)
+    /// for how it differs from a client using this transport.
+    pub fn into_typed_client(self) -> (InProcessClient<M, S>, ClientHandle) {
+        InProcessClient::new(self.io, self.metadata)
+    }
+}
+
+impl<M: Metadata, S: Middleware<M>> Transport for InProcessTransport<M, S> {
+    type Error = io::Error;
+    type Sink = InProcessSink<M, S>;
+    type Stream = InProcessStream;
+
+    fn io_pair(self) -> (Self::Sink, Self::Stream) {
+        let (responses_tx, responses_rx) = mpsc::unbounded();
+        (
+            InProcessSink {
+                io: self.io,
+                metadata: self.metadata,
+                responses_tx,
+                pushed_responses_tx: self.pushed_responses_tx,
+            },
+            InProcessStream {
+                responses_rx,
+                responses_done: false,
+                pending: FuturesUnordered::new(),
+                push_rx: self.push_rx,
+            },
+        )
+    }
+}
+
+impl<M: Metadata, S: Middleware<M>> DuplexTransport for InProcessTransport<M, S> {}
+
+/// The sink of an `InProcessTransport`.
+pub struct InProcessSink<M: Metadata, S: Middleware<M>> {
+    io: Arc<MetaIoHandler<M, S>>,
+    metadata: M,
+    responses_tx: mpsc::UnboundedSender<PendingResponse>,
+    pushed_responses_tx: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl<M: Metadata, S: Middleware<M>> Sink for InProcessSink<M, S> {
+    type SinkItem = String;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, payload: String) -> StartSend<String, io::Error> {
+        if serde_json::from_str::<Response>(&payload).is_ok() {
+            let payload = match self.pushed_responses_tx {
+                Some(ref pushed_responses_tx) => {
+                    match pushed_responses_tx.unbounded_send(payload) {
+                        Ok(()) => return Ok(AsyncSink::Ready),
+                        Err(e) => e.into_inner(),
+                    }
+                }
+                None => payload,
+            };
+            debug!("Dropping response to a pushed method call: {}", payload);
+            return Ok(AsyncSink::Ready);
+        }
+        let response = self.io.handle_request(&payload, self.metadata.clone());
+        self.responses_tx
+            .unbounded_send(Box::new(response))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Transport stream is gone"))?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+/// The stream of an `InProcessTransport`.
+pub struct InProcessStream {
+    responses_rx: mpsc::UnboundedReceiver<PendingResponse>,
+    responses_done: bool,
+    pending: FuturesUnordered<PendingResponse>,
+    push_rx: mpsc::Receiver<String>,
+}
+
+impl Stream for InProcessStream {
+    type Item = String;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<String>, io::Error> {
+        while !self.responses_done {
+            match self.responses_rx.poll() {
+                Ok(Async::Ready(Some(response))) => self.pending.push(response),
+                Ok(Async::Ready(None)) => self.responses_done = true,
+                Ok(Async::NotReady) => break,
+                Err(()) => unreachable!("Unbounded receivers never fail"),
+            }
+        }
+
+        loop {
+            match self.pending.poll() {
+                Ok(Async::Ready(Some(Some(response)))) => return Ok(Async::Ready(Some(response))),
+                // Notifications don't get a response.
+                Ok(Async::Ready(Some(None))) => (),
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(()) => error!("Handler failed without a response"),
+            }
+        }
+
+        match self.push_rx.poll() {
+            Ok(Async::Ready(Some(message))) => Ok(Async::Ready(Some(message))),
+            Ok(Async::Ready(None)) | Ok(Async::NotReady) => {
+                if self.responses_done && self.pending.is_empty() {
+                    Ok(Async::Ready(None))
+                } else {
+                    Ok(Async::NotReady)
+                }
+            }
+            Err(()) => unreachable!("Receivers never fail"),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use futures::future::{self, Either};
+use futures::stream::FuturesUnordered;
+use futures::sync::mpsc;
+use futures::{Async, Future, Poll, Stream};
+use jsonrpc_client_core::{ClientHandle, Error, ErrorKind, OutgoingMessage, Result};
+use jsonrpc_core::types::{Call, Id, MethodCall, Notification, Output, Request, Version};
+use jsonrpc_core::{MetaIoHandler, Metadata, Middleware, Response};
+use serde_json::Value as JsonValue;
+use tokio_timer::Delay;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+
+type PendingRequest = Box<dyn Future<Item = (), Error = ()> + Send>;
+
+/// A client handing the messages of its handles to a `jsonrpc_core` handler as typed requests, and
+/// completing the calls with the typed responses, so that nothing is serialized to a string and
+/// parsed back. Created with
+/// [`InProcessTransport::into_typed_client`](struct.InProcessTransport.html#method.into_typed_client).
+///
+/// Requests are sent as JSON-RPC 2.0, and the entries of a batch are handled as separate requests.
+/// There is no server handler, so messages pushed through the sender of the transport can't be
+/// delivered and responses made by the handles are dropped.
+///
+/// Timeouts of method calls are implemented with `tokio-timer`, so the client has to be driven on
+/// an executor that provides a timer.
+#[must_use]
+pub struct InProcessClient<M: Metadata, S: Middleware<M>> {
+    io: Arc<MetaIoHandler<M, S>>,
+    metadata: M,
+    messages_rx: mpsc::Receiver<OutgoingMessage>,
+    messages_done: bool,
+    next_id: u64,
+    pending: FuturesUnordered<PendingRequest>,
+}
+
+impl<M: Metadata, S: Middleware<M>> InProcessClient<M, S> {
+    pub(crate) fn new(io: Arc<MetaIoHandler<M, S>>, metadata: M) -> (Self, ClientHandle) {
+        let (messages_tx, messages_rx) = mpsc::channel(0);
+        (
+            InProcessClient {
+                io,
+                metadata,
+                messages_rx,
+                messages_done: false,
+                next_id: 0,
+                pending: FuturesUnordered::new(),
+            },
+            ClientHandle::from_sender(messages_tx),
+        )
+    }
+
+    fn handle_message(&mut self, message: OutgoingMessage) -> PendingRequest {
+        match message {
+            OutgoingMessage::RpcCall(method, params, completion, timeout) => {
+                let id = Id::Num(self.next_id);
+                self.next_id += 1;
+                let call = Call::MethodCall(MethodCall {
+                    jsonrpc: Some(Version::V2),
+                    method,
+                    params,
+                    id,
+                });
+                let result = self.handle_call(call).then(|response| match response {
+                    Ok(Some(Response::Single(output))) => output_result(output),
+                    _ => Err(Error::from("The handler did not answer the method call")),
+                });
+                let result = match timeout {
+                    Some(timeout) => Either::A(with_timeout(result, timeout)),
+                    None => Either::B(result),
+                };
+                Box::new(result.then(move |result| {
+                    if completion.send(result).is_err() {
+                        trace!("Future for RPC call dropped already");
+                    }
+                    Ok(())
+                }))
+            }
+            OutgoingMessage::Notification(method, params, completion) => {
+                let notification = Call::Notification(Notification {
+                    jsonrpc: Some(Version::V2),
+                    method,
+                    params,
+                });
+                Box::new(self.handle_call(notification).then(move |_| {
+                    if completion.send(Ok(())).is_err() {
+                        trace!("Future for notification dropped already");
+                    }
+                    Ok(())
+                }))
+            }
+            OutgoingMessage::Batch(entries) => {
+                let mut requests = Vec::with_capacity(entries.len());
+                for entry in entries {
+                    match entry {
+                        OutgoingMessage::Batch(_) | OutgoingMessage::Response(_) => error!(
+                            "Dropping message that can't be part of a batch: {:?}",
+                            entry
+                        ),
+                        entry => requests.push(self.handle_message(entry)),
+                    }
+                }
+                Box::new(future::join_all(requests).map(|_| ()))
+            }
+            OutgoingMessage::Response(response) => {
+                debug!(
+                    "Dropping response, a typed client has no server handler: {:?}",
+                    response
+                );
+                Box::new(future::ok(()))
+            }
+        }
+    }
+
+    fn handle_call(
+        &self,
+        call: Call,
+    ) -> impl Future<Item = Option<Response>, Error = ()> + Send + 'static {
+        self.io
+            .handle_rpc_request(Request::Single(call), self.metadata.clone())
+    }
+}
+
+impl<M: Metadata, S: Middleware<M>> Future for InProcessClient<M, S> {
+    type Item = ();
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<(), Error> {
+        while !self.messages_done {
+            match self.messages_rx.poll() {
+                Ok(Async::Ready(Some(message))) => {
+                    let request = self.handle_message(message);
+                    self.pending.push(request);
+                }
+                Ok(Async::Ready(None)) => self.messages_done = true,
+                Ok(Async::NotReady) => break,
+                Err(()) => unreachable!("Receivers never fail"),
+            }
+        }
+
+        loop {
+            match self.pending.poll() {
+                Ok(Async::Ready(Some(()))) => (),
+                Ok(Async::Ready(None)) if self.messages_done => return Ok(Async::Ready(())),
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(()) => unreachable!("Pending requests never fail"),
+            }
+        }
+    }
+}
+
+fn output_result(output: Output) -> Result<JsonValue> {
+    match output {
+        Output::Success(success) => Ok(success.result),
+        Output::Failure(failure) => Err(ErrorKind::JsonRpcError(failure.error).into()),
+    }
+}
+
+/// Fails the call with `ErrorKind::Timeout` if the handler has not answered it in time.
+fn with_timeout<F>(
+    result: F,
+    timeout: Duration,
+) -> impl Future<Item = JsonValue, Error = Error> + Send
+where
+    F: Future<Item = JsonValue, Error = Error> + Send,
+{
+    let timed_out = Delay::new(Instant::now() + timeout).then(|result| match result {
+        Ok(()) => Either::A(future::err(ErrorKind::Timeout.into())),
+        Err(e) => {
+            error!("Unable to wait for the timeout of a call: {}", e);
+            Either::B(future::empty())
+        }
+    });
+    result
+        .select(timed_out)
+        .map(|(result, _)| result)
+        .map_err(|(error, _)| error)
+}
@@ -0,0 +1,141 @@
+extern crate futures;
+#[macro_use]
+extern crate jsonrpc_client_core;
+extern crate jsonrpc_client_inprocess;
+extern crate jsonrpc_core;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+use futures::sync::{mpsc, oneshot};
+use futures::{Future, Sink, Stream};
+use jsonrpc_client_core::protocol::ProtocolVersion;
+use jsonrpc_client_core::server::{types::Notification, Handler, Server};
+use jsonrpc_client_core::{Client, Transport};
+use jsonrpc_client_inprocess::InProcessTransport;
+use jsonrpc_core::{Compatibility, IoHandler, MetaIoHandler, Metadata, Params, Value};
+use tokio::runtime::current_thread::Runtime;
+
+use std::sync::Mutex;
+
+jsonrpc_client!(pub struct UpperClient {
+    pub fn to_upper(&mut self, string: &str) -> Future<String>;
+    pub fn announce(&mut self, news: &str) -> Future<()>;
+});
+
+fn to_upper(params: Params) -> jsonrpc_core::Result<Value> {
+    let (string,): (String,) = params.parse()?;
+    Ok(Value::String(string.to_uppercase()))
+}
+
+#[derive(Clone, Default)]
+struct Meta(Option<mpsc::Sender<String>>);
+
+impl Metadata for Meta {}
+
+#[test]
+fn calls_handler() {
+    let mut runtime = Runtime::new().unwrap();
+    let mut io = IoHandler::new();
+    io.add_method("to_upper", to_upper);
+
+    let (client, client_handle) = InProcessTransport::new(io).into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut client = UpperClient::new(client_handle);
+    assert_eq!("ABC", runtime.block_on(client.to_upper("abc")).unwrap());
+    assert!(runtime.block_on(client.announce("news")).is_err());
+}
+
+#[test]
+fn pushes_notifications() {
+    let mut runtime = Runtime::new().unwrap();
+    let mut io = MetaIoHandler::default();
+    io.add_method_with_meta("announce", |params: Params, meta: Meta| {
+        let (news,): (String,) = params.parse()?;
+        let notification = json!({"jsonrpc": "2.0", "method": "news", "params": [news]});
+        let mut sender = meta.0.expect("No sender in metadata");
+        sender.try_send(notification.to_string()).unwrap();
+        Ok(Value::Null)
+    });
+    let transport = InProcessTransport::with_metadata(io, |sender| Meta(Some(sender)));
+
+    let (server, server_handle) = Server::new();
+    let (client, client_handle) = Client::with_server(transport, server);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let (news_tx, news_rx) = oneshot::channel();
+    let news_tx = Mutex::new(Some(news_tx));
+    let news = Handler::Notification(Box::new(move |notification: Notification| {
+        if let Some(news_tx) = news_tx.lock().unwrap().take() {
+            let _ = news_tx.send(notification.params);
+        }
+        Box::new(futures::future::ok(()))
+    }));
+    runtime
+        .block_on(server_handle.add("news".to_owned(), news))
+        .unwrap();
+
+    let mut client = UpperClient::new(client_handle);
+    runtime.block_on(client.announce("extra")).unwrap();
+    let params = runtime.block_on(news_rx).unwrap();
+    assert_eq!(json!(["extra"]), serde_json::to_value(params).unwrap());
+}
+
+#[test]
+fn legacy_protocol_version() {
+    let mut runtime = Runtime::new().unwrap();
+    let mut io = MetaIoHandler::with_compatibility(Compatibility::V1);
+    io.add_method("to_upper", to_upper);
+
+    let (client, client_handle) = InProcessTransport::with_metadata(io, |_| ()).into_client();
+    let client = client.protocol_version(ProtocolVersion::V1);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut client = UpperClient::new(client_handle);
+    assert_eq!("ABC", runtime.block_on(client.to_upper("abc")).unwrap());
+}
+
+#[test]
+fn routes_responses_to_pushed_calls() {
+    let mut runtime = Runtime::new().unwrap();
+    let mut transport = InProcessTransport::new(IoHandler::new());
+    let sender = transport.sender();
+    let responses = transport.responses();
+
+    let (server, server_handle) = Server::new();
+    let (client, _client_handle) = Client::with_server(transport, server);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    runtime
+        .block_on(
+            server_handle.add_method("double".to_owned(), |(x,): (u64,)| {
+                Ok::<_, jsonrpc_core::Error>(x * 2)
+            }),
+        )
+        .unwrap();
+
+    let call = json!({"jsonrpc": "2.0", "method": "double", "params": [21], "id": 1});
+    runtime.block_on(sender.send(call.to_string())).unwrap();
+    let (response, _) = runtime.block_on(responses.into_future()).ok().unwrap();
+    let response: Value = serde_json::from_str(&response.unwrap()).unwrap();
+    assert_eq!(json!({"jsonrpc": "2.0", "result": 42, "id": 1}), response);
+}
+
+#[test]
+fn typed_client() {
+    let mut runtime = Runtime::new().unwrap();
+    let mut io = IoHandler::new();
+    io.add_method("to_upper", to_upper);
+
+    let (client, client_handle) = InProcessTransport::new(io).into_typed_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut batch = client_handle.batch();
+    let batched = batch.call_method::<String>("to_upper", &["def"]);
+    runtime.block_on(batch.send()).unwrap();
+    assert_eq!("DEF", runtime.block_on(batched).unwrap());
+
+    let mut client = UpperClient::new(client_handle);
+    assert_eq!("ABC", runtime.block_on(client.to_upper("abc")).unwrap());
+    assert!(runtime
+        .block_on(client.announce("news"))
+        .unwrap_err()
+        .is_method_not_found());
+}
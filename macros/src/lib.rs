@@ -0,0 +1,688 @@
+// Copyright 2018 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An attribute macro generating JSON-RPC 2.0 clients for
+//! [`jsonrpc-client-core`](../jsonrpc_client_core/index.html) from a Rust trait.
+//!
+//! `#[jsonrpc_client]` is put on a trait with one method per RPC method. The trait is left as it
+//! is, and a struct named after the trait with a `Client` suffix is generated next to it. The
+//! struct is created from a `ClientHandle` and implements the trait by sending the calls through
+//! the handle. Methods with a default implementation are left to the trait. Attribute macros are
+//! only stable since Rust 1.30, so using this crate needs at least that version.
+//!
+//! Every client method takes `&self` or `&mut self` and returns an `RpcFuture`. The error type of
+//! the future can be any type implementing `From<jsonrpc_client_core::Error>`. How a method is
+//! called is set with a single `#[rpc(...)]` attribute on the method:
+//!
+//! * `name = "..."` calls an RPC method with another name than the Rust method.
+//! * `named_params` passes the arguments as an object keyed by the argument names instead of as an
+//!   array, and `named_params(argument = "name")` passes an argument under another name.
+//! * `idempotent` lets failed method calls be retried according to the `RetryPolicy` of the
+//!   handle.
+//! * `timeout_ms = ...` fails method calls that the server did not answer in time.
+//! * `notification` sends a notification instead of a method call. The method returns an
+//!   `RpcFuture<()>` resolving once the transport has accepted and flushed the notification.
+//! * `subscription = "..."` together with `unsubscribe = "..."` creates a subscription with
+//!   [`jsonrpc-client-pubsub`](../jsonrpc_client_pubsub/index.html). The method is called to
+//!   subscribe, `subscription` is the name of the notifications carrying the messages and
+//!   `unsubscribe` the method ending the subscription. The optional `buffer_size = ...` sets the
+//!   number of messages buffered for the subscription. Subscription methods take `&mut self` and
+//!   return an `RpcFuture<Subscription<T>, jsonrpc_client_pubsub::Error>`. A client with
+//!   subscriptions is generic over the executor of its `Subscriber`, which it is created with.
+//!
+//! Each option can be given once per method.
+//!
+//! `#[jsonrpc_client(namespace = "...")]` prefixes the names of all methods without an explicit
+//! name with the namespace and a dot, and `#[jsonrpc_client(named_params)]` passes the arguments of
+//! all methods by name.
+//!
+//! The trait only describes the client side. Server traits declared with `build_rpc_trait!` of
+//! `jsonrpc-macros` return `Result` or `BoxFuture` and can take metadata, so the same trait can't
+//! be used for both, and the methods have to be declared in each of them.
+//!
+//! ```rust,ignore
+//! #[jsonrpc_client(namespace = "storage")]
+//! pub trait Storage {
+//!     fn list(&self) -> RpcFuture<Vec<String>>;
+//!
+//!     #[rpc(idempotent, timeout_ms = 500)]
+//!     fn get<T>(&self, key: &str) -> RpcFuture<T>
+//!     where
+//!         T: DeserializeOwned + Send + 'static;
+//!
+//!     #[rpc(name = "$/changed", notification)]
+//!     fn changed(&self, key: &str) -> RpcFuture<()>;
+//! }
+//!
+//! let storage = StorageClient::new(client_handle);
+//! let keys = storage.list();
+//! ```
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{
+    AttributeArgs, FnArg, Ident, ItemTrait, Lit, Meta, NestedMeta, Pat, ReturnType, TraitItem,
+    TraitItemMethod, Type,
+};
+
+
+/// The number of messages buffered for a subscription without a `buffer_size`.
+const DEFAULT_BUFFER_SIZE: usize = 16;
+
+/// Generates a client struct implementing the trait this attribute is put on. See the
+/// [crate documentation](index.html) for the attributes of the methods.
+#[proc_macro_attribute]
+pub fn jsonrpc_client(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let item = parse_macro_input!(input as ItemTrait);
+    match expand(args, item) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// The options given to the `jsonrpc_client` attribute.
+#[derive(Default)]
+struct ClientOptions {
+    namespace: Option<String>,
+    named_params: bool,
+}
+
+/// What a client method sends to the server.
+enum MethodKind {
+    Call,
+    Notification,
+    Subscription {
+        notification: String,
+        unsubscribe: String,
+        buffer_size: usize,
+    },
+}
+
+/// The options given to the `rpc` attribute of a method.
+struct MethodOptions {
+    name: Option<String>,
+    named_params: bool,
+    renames: Vec<(Ident, String)>,
+    idempotent: bool,
+    timeout_ms: Option<u64>,
+    kind: MethodKind,
+}
+
+/// A method of the trait that is implemented by sending it to the server.
+struct ClientMethod {
+    item: TraitItemMethod,
+    args: Vec<Ident>,
+    name: String,
+    options: MethodOptions,
+}
+
+fn expand(args: AttributeArgs, mut item: ItemTrait) -> syn::Result<TokenStream2> {
+    let options = parse_client_options(args)?;
+    if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "generic traits can't be turned into a client",
+        ));
+    }
+
+    let mut methods = Vec::new();
+    for trait_item in &mut item.items {
+        match *trait_item {
+            TraitItem::Method(ref mut method) => {
+                if let Some(method) = client_method(method, &options)? {
+                    methods.push(method);
+                }
+            }
+            ref other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "a client trait can only contain methods",
+                ))
+            }
+        }
+    }
+
+    let has_subscriptions = methods.iter().any(|method| match method.options.kind {
+        MethodKind::Subscription { .. } => true,
+        _ => false,
+    });
+    let trait_name = &item.ident;
+    let client_name = Ident::new(&format!("{}Client", trait_name), trait_name.span());
+    let vis = &item.vis;
+    let struct_doc = format!(
+        "A JSON-RPC client implementing `{}`, generated by `#[jsonrpc_client]`.",
+        trait_name
+    );
+    let impls = methods.iter().map(method_impl);
+
+    let client = if has_subscriptions {
+        quote! {
+            #[doc = #struct_doc]
+            #vis struct #client_name<E>
+            where
+                E: ::jsonrpc_client_pubsub::macro_support::Executor + Clone + Send + 'static,
+            {
+                client: ::jsonrpc_client_core::ClientHandle,
+                subscriber: ::jsonrpc_client_pubsub::Subscriber<E>,
+            }
+
+            impl<E> #client_name<E>
+            where
+                E: ::jsonrpc_client_pubsub::macro_support::Executor + Clone + Send + 'static,
+            {
+                /// Creates a new RPC client backed by the given client handle, creating
+                /// subscriptions with the given subscriber.
+                pub fn new(
+                    client: ::jsonrpc_client_core::ClientHandle,
+                    subscriber: ::jsonrpc_client_pubsub::Subscriber<E>,
+                ) -> Self {
+                    #client_name { client, subscriber }
+                }
+            }
+
+            impl<E> #trait_name for #client_name<E>
+            where
+                E: ::jsonrpc_client_pubsub::macro_support::Executor + Clone + Send + 'static,
+            {
+                #(#impls)*
+            }
+        }
+    } else {
+        quote! {
+            #[doc = #struct_doc]
+            #vis struct #client_name {
+                client: ::jsonrpc_client_core::ClientHandle,
+            }
+
+            impl #client_name {
+                /// Creates a new RPC client backed by the given client handle.
+                pub fn new(client: ::jsonrpc_client_core::ClientHandle) -> Self {
+                    #client_name { client }
+                }
+            }
+
+            impl #trait_name for #client_name {
+                #(#impls)*
+            }
+        }
+    };
+
+    Ok(quote! {
+        #item
+        #client
+    })
+}
+
+fn parse_client_options(args: AttributeArgs) -> syn::Result<ClientOptions> {
+    let mut options = ClientOptions::default();
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(ref name_value))
+                if name_value.ident == "namespace" =>
+            {
+                options.namespace = Some(string_value(&name_value.lit)?);
+            }
+            NestedMeta::Meta(Meta::Word(ref word)) if word == "named_params" => {
+                options.named_params = true;
+            }
+            ref other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected `namespace = \"...\"` or `named_params`",
+                ))
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Checks a method of the trait and takes its `rpc` attribute out. Returns `None` for methods with
+/// a default implementation, which are left to the trait.
+fn client_method(
+    method: &mut TraitItemMethod,
+    client_options: &ClientOptions,
+) -> syn::Result<Option<ClientMethod>> {
+    let mut rpc_attrs = Vec::new();
+    method.attrs.retain(|attr| {
+        let is_rpc = attr.path.segments.len() == 1 && attr.path.segments[0].ident == "rpc";
+        if is_rpc {
+            rpc_attrs.push(attr.clone());
+        }
+        !is_rpc
+    });
+    if method.default.is_some() {
+        return match rpc_attrs.first() {
+            Some(attr) => Err(syn::Error::new_spanned(
+                attr,
+                "methods with a default implementation are not sent to the server",
+            )),
+            None => Ok(None),
+        };
+    }
+
+    if let Some(attr) = rpc_attrs.get(1) {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "a method can only have one `rpc` attribute",
+        ));
+    }
+
+    let mut options = MethodOptions {
+        name: None,
+        named_params: client_options.named_params,
+        renames: Vec::new(),
+        idempotent: false,
+        timeout_ms: None,
+        kind: MethodKind::Call,
+    };
+    if let Some(attr) = rpc_attrs.first() {
+        parse_method_options(attr.parse_meta()?, &mut options)?;
+    }
+
+    let sig = &method.sig;
+    for param in &sig.decl.generics.params {
+        if let syn::GenericParam::Const(ref param) = *param {
+            return Err(syn::Error::new_spanned(
+                param,
+                "const generics are not supported",
+            ));
+        }
+    }
+    let mut inputs = sig.decl.inputs.iter();
+    let mutable_receiver = match inputs.next() {
+        Some(FnArg::SelfRef(receiver)) => receiver.mutability.is_some(),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.ident,
+                "client methods must take `&self` or `&mut self`",
+            ))
+        }
+    };
+    let mut args = Vec::new();
+    for input in inputs {
+        match *input {
+            FnArg::Captured(ref arg) => match arg.pat {
+                Pat::Ident(ref pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+                    args.push(pat.ident.clone())
+                }
+                ref pat => {
+                    return Err(syn::Error::new_spanned(
+                        pat,
+                        "arguments of client methods must be plain identifiers",
+                    ))
+                }
+            },
+            ref other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "arguments of client methods must be named",
+                ))
+            }
+        }
+    }
+    for (from, _) in &options.renames {
+        if !args.contains(from) {
+            return Err(syn::Error::new_spanned(
+                from,
+                "the method has no argument with this name",
+            ));
+        }
+    }
+    if !returns_rpc_future(&sig.decl.output) {
+        return Err(syn::Error::new_spanned(
+            &sig.ident,
+            "client methods must return an `RpcFuture`",
+        ));
+    }
+    if let MethodKind::Subscription { .. } = options.kind {
+        if !mutable_receiver {
+            return Err(syn::Error::new_spanned(
+                &sig.ident,
+                "subscription methods must take `&mut self`",
+            ));
+        }
+    }
+
+    let name = match (options.name.clone(), &client_options.namespace) {
+        (Some(name), _) => name,
+        (None, Some(namespace)) => format!("{}.{}", namespace, sig.ident),
+        (None, &None) => sig.ident.to_string(),
+    };
+    Ok(Some(ClientMethod {
+        item: method.clone(),
+        args,
+        name,
+        options,
+    }))
+}
+
+fn parse_method_options(meta: Meta, options: &mut MethodOptions) -> syn::Result<()> {
+    let list = match meta {
+        Meta::List(list) => list,
+        other => {
+            return Err(syn::Error::new_spanned(
+                other,
+                "expected `#[rpc(...)]` with a list of options",
+            ))
+        }
+    };
+    let mut subscription = None;
+    let mut unsubscribe = None;
+    let mut buffer_size = None;
+    let mut notification = None;
+    // an option that only applies to method calls
+    let mut call_option = None;
+    let mut seen = Vec::new();
+    for nested in &list.nested {
+        if let NestedMeta::Meta(ref meta) = *nested {
+            let option = meta.name();
+            if seen.contains(&option) {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    format!("duplicate option `{}`", option),
+                ));
+            }
+            seen.push(option);
+        }
+        match *nested {
+            NestedMeta::Meta(Meta::Word(ref word)) if word == "named_params" => {
+                options.named_params = true;
+            }
+            NestedMeta::Meta(Meta::List(ref renames)) if renames.ident == "named_params" => {
+                options.named_params = true;
+                for rename in &renames.nested {
+                    match *rename {
+                        NestedMeta::Meta(Meta::NameValue(ref name_value)) => options
+                            .renames
+                            .push((name_value.ident.clone(), string_value(&name_value.lit)?)),
+                        ref other => {
+                            return Err(syn::Error::new_spanned(
+                                other,
+                                "expected `argument = \"name\"`",
+                            ))
+                        }
+                    }
+                }
+            }
+            NestedMeta::Meta(Meta::Word(ref word)) if word == "idempotent" => {
+                options.idempotent = true;
+                call_option = Some(word.clone());
+            }
+            NestedMeta::Meta(Meta::Word(ref word)) if word == "notification" => {
+                notification = Some(word.clone());
+            }
+            NestedMeta::Meta(Meta::NameValue(ref name_value)) => {
+                let lit = &name_value.lit;
+                if name_value.ident == "name" {
+                    options.name = Some(string_value(lit)?);
+                } else if name_value.ident == "timeout_ms" {
+                    options.timeout_ms = Some(int_value(lit)?);
+                    call_option = Some(name_value.ident.clone());
+                } else if name_value.ident == "subscription" {
+                    subscription = Some(string_value(lit)?);
+                } else if name_value.ident == "unsubscribe" {
+                    unsubscribe = Some(string_value(lit)?);
+                } else if name_value.ident == "buffer_size" {
+                    buffer_size = Some((name_value.ident.clone(), int_value(lit)? as usize));
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        &name_value.ident,
+                        "unknown option of `rpc`",
+                    ));
+                }
+            }
+            ref other => return Err(syn::Error::new_spanned(other, "unknown option of `rpc`")),
+        }
+    }
+
+    let kind = match (notification, subscription, unsubscribe) {
+        (None, None, None) => MethodKind::Call,
+        (Some(_), None, None) => MethodKind::Notification,
+        (None, Some(notification), Some(unsubscribe)) => MethodKind::Subscription {
+            notification,
+            unsubscribe,
+            buffer_size: buffer_size
+                .take()
+                .map_or(DEFAULT_BUFFER_SIZE, |(_, buffer_size)| buffer_size),
+        },
+        (None, Some(_), None) | (None, None, Some(_)) => {
+            return Err(syn::Error::new_spanned(
+                &list,
+                "subscriptions need both `subscription = \"...\"` and `unsubscribe = \"...\"`",
+            ))
+        }
+        (Some(word), _, _) => {
+            return Err(syn::Error::new_spanned(
+                word,
+                "a method can't be both a notification and a subscription",
+            ))
+        }
+    };
+    match (call_option, &kind) {
+        (Some(_), &MethodKind::Call) | (None, _) => (),
+        (Some(option), _) => {
+            return Err(syn::Error::new_spanned(
+                option,
+                "`idempotent` and `timeout_ms` only apply to method calls",
+            ))
+        }
+    }
+    if let Some((option, _)) = buffer_size {
+        return Err(syn::Error::new_spanned(
+            option,
+            "`buffer_size` only applies to subscriptions",
+        ));
+    }
+    options.kind = kind;
+    Ok(())
+}
+
+fn string_value(lit: &Lit) -> syn::Result<String> {
+    match *lit {
+        Lit::Str(ref string) => Ok(string.value()),
+        ref other => Err(syn::Error::new_spanned(other, "expected a string")),
+    }
+}
+
+fn int_value(lit: &Lit) -> syn::Result<u64> {
+    match *lit {
+        Lit::Int(ref int) => Ok(int.value()),
+        ref other => Err(syn::Error::new_spanned(other, "expected an integer")),
+    }
+}
+
+fn returns_rpc_future(output: &ReturnType) -> bool {
+    match *output {
+        ReturnType::Type(_, ref ty) => match **ty {
+            Type::Path(ref path) => path
+                .path
+                .segments
+                .iter()
+                .last()
+                .map_or(false, |segment| segment.ident == "RpcFuture"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
+
+/// Generates the implementation of a client method. Errors in the generated code point at the
+/// method.
+fn method_impl(method: &ClientMethod) -> TokenStream2 {
+    let sig = &method.item.sig;
+    let span = sig.ident.span();
+    let name = &method.name;
+    let args = &method.args;
+    let params = if method.options.named_params {
+        let arg_names = args.iter().map(|arg| {
+            method
+                .options
+                .renames
+                .iter()
+                .find(|rename| rename.0 == *arg)
+                .map_or_else(|| arg.to_string(), |rename| rename.1.clone())
+        });
+        quote_spanned! {span=>
+            ::jsonrpc_client_core::named_parameters(vec![
+                #((#arg_names, ::jsonrpc_client_core::serialize_parameter(&#args)),)*
+            ])
+        }
+    } else {
+        quote_spanned! {span=>
+            ::jsonrpc_client_core::positional_parameters(vec![
+                #(::jsonrpc_client_core::serialize_parameter(&#args),)*
+            ], false)
+        }
+    };
+    let send = match method.options.kind {
+        MethodKind::Call => {
+            let idempotent = method.options.idempotent;
+            let timeout = match method.options.timeout_ms {
+                Some(timeout_ms) => quote!(Some(::std::time::Duration::from_millis(#timeout_ms))),
+                None => quote!(None),
+            };
+            quote_spanned! {span=>
+                self.client.send_method_call(method, params, #timeout, #idempotent)
+            }
+        }
+        MethodKind::Notification => quote_spanned! {span=>
+            self.client.send_serialized_notification(method, params)
+        },
+        MethodKind::Subscription {
+            ref notification,
+            ref unsubscribe,
+            buffer_size,
+        } => quote_spanned! {span=>
+            self.subscriber.subscribe_serialized(
+                method,
+                String::from(#unsubscribe),
+                String::from(#notification),
+                #buffer_size,
+                params,
+            )
+        },
+    };
+    quote_spanned! {span=>
+        #sig {
+            let method = String::from(#name);
+            let params = #params;
+            ::jsonrpc_client_core::RpcFuture::new(::jsonrpc_client_core::Future::map_err(
+                #send,
+                ::std::convert::From::from,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_error(args: &str, item: &str) -> String {
+        let args = syn::parse_str::<syn::MetaList>(&format!("jsonrpc_client({})", args)).unwrap();
+        let item = syn::parse_str::<ItemTrait>(item).unwrap();
+        match expand(args.nested.into_iter().collect(), item) {
+            Ok(_) => panic!("Expanded invalid trait"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_traits() {
+        assert_eq!(
+            expand_error("version = 2", "trait A {}"),
+            "expected `namespace = \"...\"` or `named_params`"
+        );
+        assert_eq!(
+            expand_error("", "trait A<T> {}"),
+            "generic traits can't be turned into a client"
+        );
+        assert_eq!(
+            expand_error("", "trait A { fn a(self) -> RpcFuture<()>; }"),
+            "client methods must take `&self` or `&mut self`"
+        );
+        assert_eq!(
+            expand_error("", "trait A { fn a(&self) -> u64; }"),
+            "client methods must return an `RpcFuture`"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A { #[rpc(retries = 2)] fn a(&self) -> RpcFuture<()>; }"
+            ),
+            "unknown option of `rpc`"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A { #[rpc(subscription = \"a\")] fn a(&mut self) -> RpcFuture<()>; }"
+            ),
+            "subscriptions need both `subscription = \"...\"` and `unsubscribe = \"...\"`"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A {
+                    #[rpc(subscription = \"a\", unsubscribe = \"b\")]
+                    fn a(&self) -> RpcFuture<()>;
+                }"
+            ),
+            "subscription methods must take `&mut self`"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A { #[rpc(idempotent)] #[rpc(name = \"b\")] fn a(&self) -> RpcFuture<()>; }"
+            ),
+            "a method can only have one `rpc` attribute"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A { #[rpc(name = \"a\", name = \"b\")] fn a(&self) -> RpcFuture<()>; }"
+            ),
+            "duplicate option `name`"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A { #[rpc(timeout_ms = 5, timeout_ms = 6)] fn a(&self) -> RpcFuture<()>; }"
+            ),
+            "duplicate option `timeout_ms`"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A { #[rpc(notification, timeout_ms = 5)] fn a(&self) -> RpcFuture<()>; }"
+            ),
+            "`idempotent` and `timeout_ms` only apply to method calls"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A { #[rpc(buffer_size = 5)] fn a(&self) -> RpcFuture<()>; }"
+            ),
+            "`buffer_size` only applies to subscriptions"
+        );
+        assert_eq!(
+            expand_error(
+                "",
+                "trait A { #[rpc(named_params(b = \"c\"))] fn a(&self, a: u8) -> RpcFuture<()>; }"
+            ),
+            "the method has no argument with this name"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+extern crate futures;
+extern crate jsonrpc_client_core;
+#[macro_use]
+extern crate jsonrpc_client_macros;
+extern crate jsonrpc_client_pubsub;
+extern crate jsonrpc_client_testing;
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+use futures::{Future, Stream};
+use jsonrpc_client_core::{RpcFuture, Transport};
+use jsonrpc_client_pubsub::{SubscriberTransport, Subscription};
+use jsonrpc_client_testing::{Expectation, MockTransport};
+use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::current_thread;
+use tokio::runtime::Runtime;
+
+#[jsonrpc_client(namespace = "storage")]
+pub trait Storage {
+    fn list(&self) -> RpcFuture<Vec<String>>;
+
+    #[rpc(idempotent, timeout_ms = 50)]
+    fn get<T>(&self, key: &str) -> RpcFuture<T>
+    where
+        T: DeserializeOwned + Send + 'static;
+
+    #[rpc(name = "$/set", named_params(ttl = "expiresIn"))]
+    fn set(&mut self, key: &str, value: u64, ttl: Option<u64>) -> RpcFuture<()>;
+
+    #[rpc(notification)]
+    fn changed(&self, key: &str) -> RpcFuture<()>;
+
+    fn has(&self, key: &str) -> RpcFuture<bool> {
+        RpcFuture::new(self.get::<Option<u64>>(key).map(|value| value.is_some()))
+    }
+}
+
+#[jsonrpc_client]
+trait Chain {
+    fn block_number(&self) -> RpcFuture<u64>;
+
+    #[rpc(
+        subscription = "newHeads",
+        unsubscribe = "unsubscribe",
+        buffer_size = 4
+    )]
+    fn subscribe(
+        &mut self,
+        kind: &str,
+    ) -> RpcFuture<Subscription<u64>, jsonrpc_client_pubsub::Error>;
+}
+
+#[test]
+fn calls_methods() {
+    let mut runtime = current_thread::Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("storage.list").returns(json!(["a"])));
+    mock.expect(Expectation::method("$/set").params(json!({"key": "a", "value": 5})));
+    mock.expect(
+        Expectation::method("$/set").params(json!({"key": "b", "value": 6, "expiresIn": 60})),
+    );
+    mock.expect(
+        Expectation::method("storage.get")
+            .params(json!(["a"]))
+            .returns(5),
+    );
+    mock.expect(
+        Expectation::method("storage.get")
+            .params(json!(["b"]))
+            .returns(json!(null)),
+    );
+    mock.expect(Expectation::method("storage.changed").params(json!(["a"])));
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut storage = StorageClient::new(client_handle);
+    assert_eq!(vec!["a"], runtime.block_on(storage.list()).unwrap());
+    runtime.block_on(storage.set("a", 5, None)).unwrap();
+    runtime.block_on(storage.set("b", 6, Some(60))).unwrap();
+    let value: u64 = runtime.block_on(storage.get("a")).unwrap();
+    assert_eq!(5, value);
+    assert!(!runtime.block_on(storage.has("b")).unwrap());
+    runtime.block_on(storage.changed("a")).unwrap();
+    mock.verify().unwrap();
+}
+
+#[test]
+fn times_out() {
+    let mut runtime = current_thread::Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(
+        Expectation::method("storage.get")
+            .returns(5)
+            .after(Duration::from_secs(5)),
+    );
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let storage = StorageClient::new(client_handle);
+    let error = runtime.block_on(storage.get::<u64>("a")).unwrap_err();
+    match *error.kind() {
+        jsonrpc_client_core::ErrorKind::Timeout => (),
+        ref kind => panic!("Unexpected error: {}", kind),
+    }
+}
+
+#[test]
+fn subscribes() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("block_number").returns(7));
+    mock.expect(
+        Expectation::method("subscribe")
+            .params(json!(["heads"]))
+            .returns(1),
+    );
+
+    let (client, client_handle, subscriber) = mock.clone().subscriber_client(runtime.executor());
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut chain = ChainClient::new(client_handle, subscriber);
+    assert_eq!(7, runtime.block_on(chain.block_number()).unwrap());
+    let subscription = runtime.block_on(chain.subscribe("heads")).unwrap();
+
+    // The subscriber might not be registered with the notification handler yet, so the
+    // notification is repeated until it gets through.
+    let received = Arc::new(AtomicBool::new(false));
+    let sender = {
+        let (mock, received) = (mock.clone(), received.clone());
+        thread::spawn(move || {
+            while !received.load(Ordering::SeqCst) {
+                let head = json!({"subscription": 1, "result": 8});
+                mock.send_notification("newHeads", head).unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+        })
+    };
+    let (head, _subscription) = runtime
+        .block_on(subscription.into_future().map_err(|(e, _)| e))
+        .unwrap();
+    received.store(true, Ordering::SeqCst);
+    sender.join().unwrap();
+    assert_eq!(Some(8), head);
+    mock.verify().unwrap();
+}
@@ -3,6 +3,8 @@
 //! [here]: https://github.com/ethereum/go-ethereum/wiki/RPC-PUB-SUB
 
 extern crate futures;
+#[cfg(feature = "std-future")]
+extern crate futures_util;
 extern crate jsonrpc_client_core;
 extern crate jsonrpc_client_utils;
 #[macro_use]
@@ -24,6 +26,7 @@ use jsonrpc_client_core::server::{
 };
 use jsonrpc_client_core::{
     ClientHandle, DuplexTransport, Error as CoreError, ErrorKind as CoreErrorKind,
+    Result as CoreResult,
 };
 use serde_json::Value;
 use std::collections::BTreeMap;
@@ -44,6 +47,12 @@ error_chain!{
     }
 }
 
+/// Items used by the clients generated by `jsonrpc-client-macros`.
+#[doc(hidden)]
+pub mod macro_support {
+    pub use tokio::executor::Executor;
+}
+
 #[derive(Debug, Deserialize)]
 struct SubscriptionMessage {
     subscription: SubscriptionId,
@@ -56,7 +65,7 @@ pub struct Subscription<T: serde::de::DeserializeOwned> {
     rx: mpsc::Receiver<Value>,
     id: Option<SubscriptionId>,
     handler_chan: mpsc::UnboundedSender<SubscriberMsg>,
-    _marker: PhantomData<T>,
+    _marker: PhantomData<fn() -> T>,
 }
 
 impl<T: serde::de::DeserializeOwned> Stream for Subscription<T> {
@@ -74,6 +83,19 @@ impl<T: serde::de::DeserializeOwned> Stream for Subscription<T> {
     }
 }
 
+#[cfg(feature = "std-future")]
+impl<T: serde::de::DeserializeOwned> futures_util::Stream for Subscription<T> {
+    type Item = std::result::Result<T, CoreError>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let mut subscription = futures_util::compat::Compat01As03::new(self.get_mut());
+        futures_util::StreamExt::poll_next_unpin(&mut subscription, cx)
+    }
+}
+
 impl<T: serde::de::DeserializeOwned> Drop for Subscription<T> {
     fn drop(&mut self) {
         if let Some(id) = self.id.take() {
@@ -153,7 +175,7 @@ impl<E: Executor + Clone + Send + 'static> Subscriber<E> {
                             rx: sub_rx,
                             id: Some(id),
                             handler_chan: chan.clone(),
-                            _marker: PhantomData::<T>,
+                            _marker: PhantomData,
                         })
                     }),
             ),
@@ -161,6 +183,32 @@ impl<E: Executor + Clone + Send + 'static> Subscriber<E> {
         }
     }
 
+    /// Creates a new subscription with already serialized parameters. Primarily intended to be
+    /// used from the clients generated by `jsonrpc-client-macros`.
+    #[doc(hidden)]
+    pub fn subscribe_serialized<T>(
+        &mut self,
+        sub_method: String,
+        unsub_method: String,
+        notification_method: String,
+        buffer_size: usize,
+        sub_parameters: CoreResult<Option<Params>>,
+    ) -> impl Future<Item = Subscription<T>, Error = Error>
+    where
+        T: serde::de::DeserializeOwned + 'static,
+    {
+        match sub_parameters {
+            Ok(params) => Either::A(self.subscribe(
+                sub_method,
+                unsub_method,
+                notification_method,
+                buffer_size,
+                params,
+            )),
+            Err(e) => Either::B(future::err(e.into())),
+        }
+    }
+
     fn spawn_notification_handler(
         &mut self,
         notification_method: String,
@@ -0,0 +1,106 @@
+use super::{ErrorKind, Result, ResultExt};
+
+use serde_json::{self, Value as JsonValue};
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::Duration;
+
+
+/// Which way a recorded message went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// The client sent the message to the server.
+    Sent,
+    /// The client received the message from the server.
+    Received,
+}
+
+/// A single message passing through a `RecordingTransport`. A cassette holds one entry per line,
+/// serialized as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Which way the message went.
+    pub direction: Direction,
+    /// Milliseconds passed between creating the transport and the message passing through it.
+    pub elapsed_ms: u64,
+    /// The message. Payloads that were not valid JSON are kept as a string.
+    pub message: JsonValue,
+}
+
+impl Entry {
+    /// Creates an entry for a payload that passed through a transport.
+    pub fn new(direction: Direction, elapsed: Duration, payload: &str) -> Self {
+        Entry {
+            direction,
+            elapsed_ms: elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis()),
+            message: serde_json::from_str(payload)
+                .unwrap_or_else(|_| JsonValue::String(payload.to_owned())),
+        }
+    }
+
+    /// Returns the message as it was sent over the transport.
+    pub fn payload(&self) -> String {
+        match self.message {
+            JsonValue::String(ref payload) => payload.clone(),
+            ref message => message.to_string(),
+        }
+    }
+
+    /// Writes the entry as a single line.
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        let mut line = serde_json::to_vec(self).chain_err(|| ErrorKind::CassetteError)?;
+        line.push(b'\n');
+        writer
+            .write_all(&line)
+            .and_then(|_| writer.flush())
+            .chain_err(|| ErrorKind::CassetteError)
+    }
+}
+
+/// Reads all entries of the cassette at the given path.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<Entry>> {
+    let file = File::open(path).chain_err(|| ErrorKind::CassetteError)?;
+    read_from(BufReader::new(file))
+}
+
+/// Reads all entries of a cassette. Empty lines are skipped.
+pub fn read_from(reader: impl BufRead) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line.chain_err(|| ErrorKind::CassetteError)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(serde_json::from_str(&line).chain_err(|| ErrorKind::CassetteError)?);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip() {
+        let entries = vec![
+            Entry::new(
+                Direction::Sent,
+                Duration::from_millis(5),
+                r#"{"jsonrpc":"2.0","method":"ping","params":[],"id":1}"#,
+            ),
+            Entry::new(Direction::Received, Duration::from_millis(12), "not json"),
+        ];
+        let mut cassette = Vec::new();
+        for entry in &entries {
+            entry.write_to(&mut cassette).unwrap();
+        }
+
+        let read_entries = read_from(&cassette[..]).unwrap();
+        assert_eq!(read_entries, entries);
+        assert_eq!(read_entries[0].elapsed_ms, 5);
+        assert_eq!(read_entries[1].payload(), "not json");
+    }
+}
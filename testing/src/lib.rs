@@ -0,0 +1,72 @@
+// Copyright 2018 Amagicom AB.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Transports for testing code built on `jsonrpc-client-core` without talking to a live server.
+//!
+//! A [`RecordingTransport`](struct.RecordingTransport.html) wraps a real transport and writes
+//! every message passing through it to a cassette file. A
+//! [`ReplayTransport`](struct.ReplayTransport.html) later serves the recorded exchanges back,
+//! so the same test can run offline and deterministically.
+//!
+//! A [`MockTransport`](struct.MockTransport.html) replies to the client according to scripted
+//! [`Expectation`s](struct.Expectation.html) instead, and can send notifications and requests to
+//! the client as if it was the server.
+
+#![deny(missing_docs)]
+
+#[macro_use]
+extern crate error_chain;
+#[macro_use]
+extern crate futures;
+extern crate jsonrpc_client_core;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde;
+extern crate serde_json;
+extern crate tokio_timer;
+
+/// Module containing the cassette format shared by the recording and the replaying transport.
+pub mod cassette;
+
+mod record;
+pub use record::{RecordingSink, RecordingStream, RecordingTransport};
+
+mod replay;
+pub use replay::{ReplaySink, ReplayStream, ReplayTransport};
+
+mod mock;
+pub use mock::{Expectation, MockSink, MockStream, MockTransport};
+
+
+error_chain! {
+    errors {
+        /// The wrapped transport failed.
+        TransportError {
+            description("The wrapped transport failed")
+        }
+        /// Unable to read or write a cassette.
+        CassetteError {
+            description("Unable to read or write the cassette")
+        }
+        /// The client sent a request that does not match any recorded exchange.
+        UnexpectedRequest(request: String) {
+            description("No recorded exchange matches the request")
+            display("No recorded exchange matches the request: {}", request)
+        }
+        /// The client of a mock transport has been dropped.
+        Disconnected {
+            description("The client is gone")
+        }
+        /// A mock transport did not get the calls it expected.
+        ExpectationsNotMet(problems: String) {
+            description("The expectations of the mock transport were not met")
+            display("The expectations of the mock transport were not met:\n{}", problems)
+        }
+    }
+}
@@ -0,0 +1,461 @@
+use super::{Error, ErrorKind, Result, ResultExt};
+
+use futures::sync::{mpsc, oneshot};
+use futures::{Async, AsyncSink, Future, Poll, Sink, StartSend, Stream};
+use jsonrpc_client_core::server::types::{
+    Call, Error as RpcError, ErrorCode, Failure, Id, MethodCall, Notification, Output, Params,
+    Request, Response, Success, Version,
+};
+use jsonrpc_client_core::{DuplexTransport, Transport};
+use serde_json::{self, Value as JsonValue};
+use tokio_timer::Delay;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+
+type ParamsMatcher = Box<dyn Fn(&JsonValue) -> bool + Send>;
+type Reply = (Option<Instant>, String);
+
+/// A scripted reply to method calls or notifications the client is expected to send to a
+/// `MockTransport`.
+pub struct Expectation {
+    method: String,
+    params: Option<ParamsMatcher>,
+    params_description: String,
+    result: ::std::result::Result<JsonValue, RpcError>,
+    delay: Option<Duration>,
+    times: usize,
+    calls: usize,
+}
+
+impl Expectation {
+    /// Expects a single call to the given method, with any parameters. The call gets `null` as
+    /// result unless something else is set.
+    pub fn method(method: impl Into<String>) -> Self {
+        Expectation {
+            method: method.into(),
+            params: None,
+            params_description: "any params".to_owned(),
+            result: Ok(JsonValue::Null),
+            delay: None,
+            times: 1,
+            calls: 0,
+        }
+    }
+
+    /// Only matches calls with exactly these parameters.
+    pub fn params(mut self, params: JsonValue) -> Self {
+        self.params_description = params.to_string();
+        self.params = Some(Box::new(move |actual| *actual == params));
+        self
+    }
+
+    /// Only matches calls whose parameters the given predicate returns true for. Missing
+    /// parameters are passed to the predicate as `null`.
+    pub fn params_matching<F>(mut self, matches: F) -> Self
+    where
+        F: Fn(&JsonValue) -> bool + Send + 'static,
+    {
+        self.params_description = "matching params".to_owned();
+        self.params = Some(Box::new(matches));
+        self
+    }
+
+    /// Replies with the given result.
+    pub fn returns(mut self, result: impl Into<JsonValue>) -> Self {
+        self.result = Ok(result.into());
+        self
+    }
+
+    /// Replies with the given JSON-RPC 2.0 error.
+    pub fn fails_with(mut self, error: RpcError) -> Self {
+        self.result = Err(error);
+        self
+    }
+
+    /// Waits the given time before replying.
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Expects the given number of matching calls instead of one.
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = times;
+        self
+    }
+
+    fn matches(&self, method: &str, params: &JsonValue) -> bool {
+        self.calls < self.times
+            && self.method == method
+            && self.params.as_ref().map_or(true, |matches| matches(params))
+    }
+
+    fn output(&self, id: Id) -> Output {
+        match self.result {
+            Ok(ref result) => Output::Success(Success {
+                jsonrpc: Some(Version::V2),
+                result: result.clone(),
+                id,
+            }),
+            Err(ref error) => Output::Failure(Failure {
+                jsonrpc: Some(Version::V2),
+                error: error.clone(),
+                id,
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} with {}, called {} of {} times",
+            self.method, self.params_description, self.calls, self.times
+        )
+    }
+}
+
+/// An in-memory transport replying to the client according to scripted expectations, for testing
+/// clients without a server.
+///
+/// Clones of the transport share the same expectations, so a clone can be turned into a client
+/// while the test keeps the original for scripting it. Calls that don't match any expectation get
+/// a method not found error, and make `verify` fail. Delayed replies are implemented with
+/// `tokio-timer`, so the client has to be driven on an executor that provides a timer.
+///
+/// ```rust,ignore
+/// let mock = MockTransport::new();
+/// mock.expect(Expectation::method("to_upper").params(json!(["abc"])).returns("ABC"));
+/// let (client, client_handle) = mock.clone().into_client();
+/// // ...
+/// mock.verify().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+    replies_tx: mpsc::UnboundedSender<Reply>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    expectations: Vec<Expectation>,
+    unexpected: Vec<String>,
+    pending_requests: HashMap<Id, oneshot::Sender<Output>>,
+    next_request_id: u64,
+    replies_rx: Option<mpsc::UnboundedReceiver<Reply>>,
+}
+
+impl MockTransport {
+    /// Creates a new transport without any expectations.
+    pub fn new() -> Self {
+        let (replies_tx, replies_rx) = mpsc::unbounded();
+        MockTransport {
+            state: Arc::new(Mutex::new(MockState {
+                expectations: Vec::new(),
+                unexpected: Vec::new(),
+                pending_requests: HashMap::new(),
+                next_request_id: 0,
+                replies_rx: Some(replies_rx),
+            })),
+            replies_tx,
+        }
+    }
+
+    /// Adds an expectation. Calls are matched against the expectations in the order they were
+    /// added, skipping expectations that have already been called as many times as expected.
+    pub fn expect(&self, expectation: Expectation) {
+        self.lock().expectations.push(expectation);
+    }
+
+    /// Sends a notification to the client, as if the server sent it.
+    pub fn send_notification(&self, method: impl Into<String>, params: JsonValue) -> Result<()> {
+        let notification = Notification {
+            jsonrpc: Some(Version::V2),
+            method: method.into(),
+            params: to_params(params)?,
+        };
+        self.send(&notification)
+    }
+
+    /// Sends a method call to the client, as if the server sent it. The returned future resolves
+    /// to the response of the client.
+    pub fn send_request(
+        &self,
+        method: impl Into<String>,
+        params: JsonValue,
+    ) -> impl Future<Item = Output, Error = Error> {
+        let (response_tx, response_rx) = oneshot::channel();
+        let result = to_params(params).and_then(|params| {
+            let id = {
+                let mut state = self.lock();
+                state.next_request_id += 1;
+                let id = Id::Str(format!("mock-{}", state.next_request_id));
+                state.pending_requests.insert(id.clone(), response_tx);
+                id
+            };
+            self.send(&MethodCall {
+                jsonrpc: Some(Version::V2),
+                method: method.into(),
+                params,
+                id,
+            })
+        });
+        ::futures::future::result(result)
+            .and_then(|_| response_rx.map_err(|_| Error::from(ErrorKind::Disconnected)))
+    }
+
+    /// Checks that every expectation was called as many times as expected, and that no call was
+    /// unexpected.
+    pub fn verify(&self) -> Result<()> {
+        let state = self.lock();
+        let mut problems: Vec<String> = state
+            .expectations
+            .iter()
+            .filter(|expectation| expectation.calls != expectation.times)
+            .map(|expectation| format!("Expected {:?}", expectation))
+            .collect();
+        problems.extend(
+            state
+                .unexpected
+                .iter()
+                .map(|call| format!("Unexpected {}", call)),
+        );
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            bail!(ErrorKind::ExpectationsNotMet(problems.join("\n")))
+        }
+    }
+
+    fn send(&self, message: &impl ::serde::Serialize) -> Result<()> {
+        let payload = serde_json::to_string(message).chain_err(|| ErrorKind::TransportError)?;
+        self.replies_tx
+            .unbounded_send((None, payload))
+            .map_err(|_| ErrorKind::Disconnected.into())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        lock(&self.state)
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for MockTransport {
+    type Error = Error;
+    type Sink = MockSink;
+    type Stream = MockStream;
+
+    fn io_pair(self) -> (Self::Sink, Self::Stream) {
+        let replies_rx = self
+            .lock()
+            .replies_rx
+            .take()
+            .expect("A MockTransport can only be used by a single client");
+        (
+            MockSink {
+                state: self.state,
+                replies_tx: self.replies_tx,
+            },
+            MockStream {
+                replies_rx,
+                delayed: Vec::new(),
+            },
+        )
+    }
+}
+
+impl DuplexTransport for MockTransport {}
+
+/// The sink of a `MockTransport`.
+#[derive(Debug)]
+pub struct MockSink {
+    state: Arc<Mutex<MockState>>,
+    replies_tx: mpsc::UnboundedSender<Reply>,
+}
+
+impl MockSink {
+    fn handle_payload(&mut self, payload: &str) -> Result<()> {
+        let message: JsonValue = serde_json::from_str(payload)
+            .chain_err(|| ErrorKind::UnexpectedRequest(payload.into()))?;
+        let is_response = match message {
+            JsonValue::Array(ref entries) => {
+                entries.iter().all(|entry| entry.get("method").is_none())
+            }
+            ref entry => entry.get("method").is_none(),
+        };
+        if is_response {
+            let response = serde_json::from_value(message)
+                .chain_err(|| ErrorKind::UnexpectedRequest(payload.into()))?;
+            self.handle_response(response);
+            return Ok(());
+        }
+
+        let request = serde_json::from_value(message)
+            .chain_err(|| ErrorKind::UnexpectedRequest(payload.into()))?;
+        let reply = match request {
+            Request::Single(call) => self
+                .handle_call(call)
+                .map(|(deadline, output)| (deadline, serde_json::to_string(&output))),
+            Request::Batch(calls) => {
+                let mut latest_deadline = None;
+                let mut outputs = Vec::new();
+                for call in calls {
+                    if let Some((deadline, output)) = self.handle_call(call) {
+                        latest_deadline = ::std::cmp::max(latest_deadline, deadline);
+                        outputs.push(output);
+                    }
+                }
+                if outputs.is_empty() {
+                    None
+                } else {
+                    Some((latest_deadline, serde_json::to_string(&outputs)))
+                }
+            }
+        };
+        if let Some((deadline, reply)) = reply {
+            let reply = reply.chain_err(|| ErrorKind::TransportError)?;
+            if self.replies_tx.unbounded_send((deadline, reply)).is_err() {
+                trace!("Mock stream dropped, not replying");
+            }
+        }
+        Ok(())
+    }
+
+    /// Matches a call against the expectations, returning the reply to it if it is a method call.
+    fn handle_call(&mut self, call: Call) -> Option<(Option<Instant>, Output)> {
+        let (method, params, id) = match call {
+            Call::MethodCall(call) => (call.method, call.params, Some(call.id)),
+            Call::Notification(notification) => (notification.method, notification.params, None),
+            Call::Invalid(id) => (String::new(), None, Some(id)),
+        };
+        let params = params.map_or(JsonValue::Null, |params| {
+            serde_json::to_value(params).unwrap_or(JsonValue::Null)
+        });
+
+        let mut state = lock(&self.state);
+        match state
+            .expectations
+            .iter_mut()
+            .find(|expectation| expectation.matches(&method, &params))
+        {
+            Some(expectation) => {
+                expectation.calls += 1;
+                let deadline = expectation.delay.map(|delay| Instant::now() + delay);
+                id.map(|id| (deadline, expectation.output(id)))
+            }
+            None => {
+                debug!("No expectation matches call to {} with {}", method, params);
+                state
+                    .unexpected
+                    .push(format!("call to {} with {}", method, params));
+                id.map(|id| {
+                    let mut error = RpcError::new(ErrorCode::MethodNotFound);
+                    error.message = format!("No expectation matches call to {}", method);
+                    let output = Output::Failure(Failure {
+                        jsonrpc: Some(Version::V2),
+                        error,
+                        id,
+                    });
+                    (None, output)
+                })
+            }
+        }
+    }
+
+    fn handle_response(&mut self, response: Response) {
+        let outputs = match response {
+            Response::Single(output) => vec![output],
+            Response::Batch(outputs) => outputs,
+        };
+        let mut state = lock(&self.state);
+        for output in outputs {
+            match state.pending_requests.remove(output.id()) {
+                Some(response_tx) => {
+                    let _ = response_tx.send(output);
+                }
+                None => {
+                    let output = serde_json::to_string(&output).unwrap_or_default();
+                    state.unexpected.push(format!("response {}", output));
+                }
+            }
+        }
+    }
+}
+
+impl Sink for MockSink {
+    type SinkItem = String;
+    type SinkError = Error;
+
+    fn start_send(&mut self, payload: String) -> StartSend<String, Error> {
+        self.handle_payload(&payload)?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+/// The stream of a `MockTransport`.
+#[derive(Debug)]
+pub struct MockStream {
+    replies_rx: mpsc::UnboundedReceiver<Reply>,
+    delayed: Vec<(Delay, String)>,
+}
+
+impl Stream for MockStream {
+    type Item = String;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<String>, Error> {
+        loop {
+            match self.replies_rx.poll() {
+                Ok(Async::Ready(Some((None, reply)))) => return Ok(Async::Ready(Some(reply))),
+                Ok(Async::Ready(Some((Some(deadline), reply)))) => {
+                    self.delayed.push((Delay::new(deadline), reply));
+                }
+                Ok(Async::Ready(None)) if self.delayed.is_empty() => return Ok(Async::Ready(None)),
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(()) => unreachable!("Unbounded receivers never fail"),
+            }
+        }
+
+        for index in 0..self.delayed.len() {
+            if let Async::Ready(()) = self.delayed[index]
+                .0
+                .poll()
+                .chain_err(|| ErrorKind::TransportError)?
+            {
+                let (_, reply) = self.delayed.remove(index);
+                return Ok(Async::Ready(Some(reply)));
+            }
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+fn to_params(params: JsonValue) -> Result<Option<Params>> {
+    match params {
+        JsonValue::Null => Ok(None),
+        params => serde_json::from_value(params)
+            .map(Some)
+            .chain_err(|| ErrorKind::TransportError),
+    }
+}
+
+fn lock(state: &Mutex<MockState>) -> MutexGuard<'_, MockState> {
+    match state.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
@@ -0,0 +1,156 @@
+use super::cassette::{Direction, Entry};
+use super::{Error, ErrorKind, Result, ResultExt};
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use jsonrpc_client_core::call_failures::CallFailures;
+use jsonrpc_client_core::{DuplexTransport, Transport};
+
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+
+/// Wraps a transport and records every message sent and received through it to a cassette, which
+/// can later be served back by a `ReplayTransport`.
+pub struct RecordingTransport<T: Transport> {
+    inner: T,
+    recorder: Recorder,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    /// Wraps the given transport, writing the cassette to the given writer.
+    pub fn new(inner: T, cassette: impl Write + Send + 'static) -> Self {
+        RecordingTransport {
+            inner,
+            recorder: Recorder {
+                cassette: Arc::new(Mutex::new(Box::new(cassette))),
+                started: Instant::now(),
+            },
+        }
+    }
+
+    /// Wraps the given transport, writing the cassette to a file at the given path. An existing
+    /// file is overwritten.
+    pub fn create(inner: T, path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::create(path).chain_err(|| ErrorKind::CassetteError)?;
+        Ok(Self::new(inner, file))
+    }
+}
+
+impl<T: Transport> fmt::Debug for RecordingTransport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RecordingTransport")
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    type Error = Error;
+    type Sink = RecordingSink<T::Sink>;
+    type Stream = RecordingStream<T::Stream>;
+
+    fn io_pair(self) -> (Self::Sink, Self::Stream) {
+        let (sink, stream) = self.inner.io_pair();
+        (
+            RecordingSink {
+                inner: sink,
+                recorder: self.recorder.clone(),
+            },
+            RecordingStream {
+                inner: stream,
+                recorder: self.recorder,
+            },
+        )
+    }
+
+    fn set_call_failures(&mut self, call_failures: CallFailures) {
+        self.inner.set_call_failures(call_failures)
+    }
+}
+
+impl<T: DuplexTransport> DuplexTransport for RecordingTransport<T> {}
+
+/// The sink of a `RecordingTransport`.
+pub struct RecordingSink<S> {
+    inner: S,
+    recorder: Recorder,
+}
+
+impl<S> Sink for RecordingSink<S>
+where
+    S: Sink<SinkItem = String>,
+    S::SinkError: ::std::error::Error + Send + 'static,
+{
+    type SinkItem = String;
+    type SinkError = Error;
+
+    fn start_send(&mut self, payload: String) -> StartSend<String, Error> {
+        let entry = Entry::new(Direction::Sent, self.recorder.started.elapsed(), &payload);
+        match self
+            .inner
+            .start_send(payload)
+            .chain_err(|| ErrorKind::TransportError)?
+        {
+            AsyncSink::Ready => {
+                self.recorder.record(&entry)?;
+                Ok(AsyncSink::Ready)
+            }
+            AsyncSink::NotReady(payload) => Ok(AsyncSink::NotReady(payload)),
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Error> {
+        self.inner
+            .poll_complete()
+            .chain_err(|| ErrorKind::TransportError)
+    }
+
+    fn close(&mut self) -> Poll<(), Error> {
+        self.inner.close().chain_err(|| ErrorKind::TransportError)
+    }
+}
+
+/// The stream of a `RecordingTransport`.
+pub struct RecordingStream<S> {
+    inner: S,
+    recorder: Recorder,
+}
+
+impl<S> Stream for RecordingStream<S>
+where
+    S: Stream<Item = String>,
+    S::Error: ::std::error::Error + Send + 'static,
+{
+    type Item = String;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<String>, Error> {
+        let payload = try_ready!(self.inner.poll().chain_err(|| ErrorKind::TransportError));
+        if let Some(ref payload) = payload {
+            let elapsed = self.recorder.started.elapsed();
+            self.recorder
+                .record(&Entry::new(Direction::Received, elapsed, payload))?;
+        }
+        Ok(Async::Ready(payload))
+    }
+}
+
+/// The cassette shared by the sink and the stream of a recording transport.
+#[derive(Clone)]
+struct Recorder {
+    cassette: Arc<Mutex<Box<dyn Write + Send>>>,
+    started: Instant,
+}
+
+impl Recorder {
+    fn record(&self, entry: &Entry) -> Result<()> {
+        trace!("Recording {:?}", entry);
+        let mut cassette = match self.cassette.lock() {
+            Ok(cassette) => cassette,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        entry.write_to(&mut *cassette)
+    }
+}
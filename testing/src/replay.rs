@@ -0,0 +1,290 @@
+use super::cassette::{self, Direction, Entry};
+use super::{Error, ErrorKind, Result};
+
+use futures::sync::mpsc;
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use jsonrpc_client_core::{DuplexTransport, Transport};
+use serde_json::{self, Value as JsonValue};
+
+use std::path::Path;
+
+
+/// Serves the exchanges recorded by a `RecordingTransport` back to a client, without a server.
+///
+/// Requests are matched against the recorded requests by method and parameters, ignoring their
+/// ids. When a request matches, the responses recorded for it are sent back with the ids of the
+/// request, followed by the requests and notifications the server sent before the client sent its
+/// next request. Every recorded request is replayed at most once, in the order they were recorded.
+/// Replies are delivered right away, regardless of the timing in the cassette.
+///
+/// A request that doesn't match any recorded request makes the transport fail with
+/// `ErrorKind::UnexpectedRequest`. Responses the client sends to requests from the server are not
+/// checked.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    initial: Vec<JsonValue>,
+    exchanges: Vec<Exchange>,
+}
+
+/// A recorded request together with everything the server sent in reply to it.
+#[derive(Debug)]
+struct Exchange {
+    key: JsonValue,
+    ids: Vec<Option<JsonValue>>,
+    replies: Vec<JsonValue>,
+    replayed: bool,
+}
+
+impl ReplayTransport {
+    /// Creates a transport replaying the given cassette entries.
+    pub fn new(entries: Vec<Entry>) -> Self {
+        let mut initial = Vec::new();
+        let mut exchanges: Vec<Exchange> = Vec::new();
+        for entry in entries {
+            match entry.direction {
+                Direction::Sent => {
+                    if let Some(key) = request_key(&entry.message) {
+                        exchanges.push(Exchange {
+                            key,
+                            ids: request_ids(&entry.message),
+                            replies: Vec::new(),
+                            replayed: false,
+                        });
+                    }
+                }
+                Direction::Received => {
+                    let response_ids = response_ids(&entry.message);
+                    let exchange = if response_ids.is_empty() {
+                        exchanges.last_mut()
+                    } else {
+                        exchanges.iter_mut().rev().find(|exchange| {
+                            exchange
+                                .ids
+                                .iter()
+                                .any(|id| id.as_ref().map_or(false, |id| response_ids.contains(id)))
+                        })
+                    };
+                    match exchange {
+                        Some(exchange) => exchange.replies.push(entry.message),
+                        None => initial.push(entry.message),
+                    }
+                }
+            }
+        }
+        ReplayTransport { initial, exchanges }
+    }
+
+    /// Creates a transport replaying the cassette at the given path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        cassette::read(path).map(Self::new)
+    }
+}
+
+impl Transport for ReplayTransport {
+    type Error = Error;
+    type Sink = ReplaySink;
+    type Stream = ReplayStream;
+
+    fn io_pair(self) -> (Self::Sink, Self::Stream) {
+        let (replies_tx, replies_rx) = mpsc::unbounded();
+        for message in self.initial {
+            let _ = replies_tx.unbounded_send(message.to_string());
+        }
+        (
+            ReplaySink {
+                exchanges: self.exchanges,
+                replies_tx,
+            },
+            ReplayStream { replies_rx },
+        )
+    }
+}
+
+impl DuplexTransport for ReplayTransport {}
+
+/// The sink of a `ReplayTransport`.
+#[derive(Debug)]
+pub struct ReplaySink {
+    exchanges: Vec<Exchange>,
+    replies_tx: mpsc::UnboundedSender<String>,
+}
+
+impl ReplaySink {
+    fn replay(&mut self, payload: &str) -> Result<()> {
+        let message: JsonValue = serde_json::from_str(payload)
+            .map_err(|_| ErrorKind::UnexpectedRequest(payload.to_owned()))?;
+        let key = match request_key(&message) {
+            Some(key) => key,
+            None => {
+                trace!("Not replaying anything for response {}", payload);
+                return Ok(());
+            }
+        };
+        let exchange = self
+            .exchanges
+            .iter_mut()
+            .find(|exchange| !exchange.replayed && exchange.key == key)
+            .ok_or_else(|| ErrorKind::UnexpectedRequest(payload.to_owned()))?;
+        exchange.replayed = true;
+
+        let ids: Vec<(JsonValue, JsonValue)> = exchange
+            .ids
+            .iter()
+            .zip(request_ids(&message))
+            .filter_map(|ids| match ids {
+                (Some(recorded_id), Some(id)) => Some((recorded_id.clone(), id)),
+                _ => None,
+            })
+            .collect();
+        for reply in &exchange.replies {
+            let reply = replace_response_ids(reply.clone(), &ids);
+            if self.replies_tx.unbounded_send(reply.to_string()).is_err() {
+                trace!("Replay stream dropped, not replaying {}", reply);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Sink for ReplaySink {
+    type SinkItem = String;
+    type SinkError = Error;
+
+    fn start_send(&mut self, payload: String) -> StartSend<String, Error> {
+        self.replay(&payload)?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+/// The stream of a `ReplayTransport`.
+#[derive(Debug)]
+pub struct ReplayStream {
+    replies_rx: mpsc::UnboundedReceiver<String>,
+}
+
+impl Stream for ReplayStream {
+    type Item = String;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<String>, Error> {
+        match self.replies_rx.poll() {
+            Ok(result) => Ok(result),
+            Err(()) => unreachable!("Unbounded receivers never fail"),
+        }
+    }
+}
+
+/// Returns what identifies a request or a batch of requests, its methods and parameters. Returns
+/// `None` for anything that isn't a request.
+fn request_key(message: &JsonValue) -> Option<JsonValue> {
+    match *message {
+        JsonValue::Array(ref calls) => calls
+            .iter()
+            .map(call_key)
+            .collect::<Option<Vec<_>>>()
+            .map(JsonValue::Array),
+        ref call => call_key(call),
+    }
+}
+
+fn call_key(call: &JsonValue) -> Option<JsonValue> {
+    let method = call.get("method")?.clone();
+    let params = call.get("params").cloned().unwrap_or(JsonValue::Null);
+    Some(JsonValue::Array(vec![method, params]))
+}
+
+/// Returns the ids of the calls in a request or a batch of requests, `None` for notifications.
+fn request_ids(message: &JsonValue) -> Vec<Option<JsonValue>> {
+    match *message {
+        JsonValue::Array(ref calls) => calls.iter().map(|call| call.get("id").cloned()).collect(),
+        ref call => vec![call.get("id").cloned()],
+    }
+}
+
+/// Returns the ids of the responses in a response or a batch of responses.
+fn response_ids(message: &JsonValue) -> Vec<JsonValue> {
+    match *message {
+        JsonValue::Array(ref outputs) => outputs.iter().filter_map(response_id).collect(),
+        ref output => response_id(output).into_iter().collect(),
+    }
+}
+
+fn response_id(output: &JsonValue) -> Option<JsonValue> {
+    if output.get("method").is_some() {
+        return None;
+    }
+    output.get("id").cloned()
+}
+
+/// Replaces the recorded ids of responses with the ids of the replayed request.
+fn replace_response_ids(message: JsonValue, ids: &[(JsonValue, JsonValue)]) -> JsonValue {
+    match message {
+        JsonValue::Array(outputs) => JsonValue::Array(
+            outputs
+                .into_iter()
+                .map(|output| replace_response_ids(output, ids))
+                .collect(),
+        ),
+        JsonValue::Object(mut output) => {
+            if !output.contains_key("method") {
+                let id = output
+                    .get("id")
+                    .and_then(|id| ids.iter().find(|(recorded_id, _)| recorded_id == id))
+                    .map(|(_, id)| id.clone());
+                if let Some(id) = id {
+                    output.insert("id".to_owned(), id);
+                }
+            }
+            JsonValue::Object(output)
+        }
+        message => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::Future;
+    use std::time::Duration;
+
+    fn entry(direction: Direction, message: &str) -> Entry {
+        Entry::new(direction, Duration::from_millis(0), message)
+    }
+
+    #[test]
+    fn replays_with_new_ids() {
+        let transport = ReplayTransport::new(vec![
+            entry(
+                Direction::Sent,
+                r#"{"jsonrpc":"2.0","method":"subscribe","params":["news"],"id":1}"#,
+            ),
+            entry(
+                Direction::Received,
+                r#"{"jsonrpc":"2.0","result":"s","id":1}"#,
+            ),
+            entry(
+                Direction::Received,
+                r#"{"jsonrpc":"2.0","method":"news","params":{"subscription":"s"}}"#,
+            ),
+        ]);
+        let (mut sink, stream) = transport.io_pair();
+
+        let request = r#"{"jsonrpc":"2.0","method":"subscribe","params":["news"],"id":7}"#;
+        sink.start_send(request.to_owned()).unwrap();
+        let replies = stream.take(2).collect().wait().unwrap();
+        let replies: Vec<JsonValue> = replies
+            .iter()
+            .map(|reply| serde_json::from_str(reply).unwrap())
+            .collect();
+        assert_eq!(replies[0]["id"], 7);
+        assert_eq!(replies[0]["result"], "s");
+        assert_eq!(replies[1]["method"], "news");
+
+        // The exchange has been replayed already.
+        assert!(sink.start_send(request.to_owned()).is_err());
+    }
+}
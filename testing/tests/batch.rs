@@ -0,0 +1,221 @@
+extern crate futures;
+extern crate jsonrpc_client_core;
+extern crate jsonrpc_client_testing;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+mod common;
+
+use common::channel_transport;
+use futures::sync::oneshot;
+use futures::Future;
+use jsonrpc_client_core::protocol::ProtocolVersion;
+use jsonrpc_client_core::server::types::ErrorCode;
+use jsonrpc_client_core::{ErrorKind, Transport};
+use jsonrpc_client_testing::{Expectation, MockTransport};
+use tokio::runtime::current_thread::Runtime;
+
+use std::collections::HashMap;
+
+#[test]
+fn batch_with_notifications() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(
+        Expectation::method("to_upper")
+            .params(json!(["abc"]))
+            .returns("ABC"),
+    );
+    mock.expect(Expectation::method("log").params(json!(["batched"])));
+    mock.expect(
+        Expectation::method("to_upper")
+            .params(json!(["def"]))
+            .returns("DEF"),
+    );
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut batch = client_handle.batch();
+    let first = batch.call_method::<String>("to_upper", &["abc"]);
+    let logged = batch.send_notification("log", &["batched"]);
+    let second = batch.call_method::<String>("to_upper", &["def"]);
+    assert_eq!(3, batch.len());
+    runtime.block_on(batch.send()).unwrap();
+
+    assert_eq!("DEF", runtime.block_on(second).unwrap());
+    assert_eq!("ABC", runtime.block_on(first).unwrap());
+    runtime.block_on(logged).unwrap();
+    mock.verify().unwrap();
+}
+
+#[test]
+fn empty_batch() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("to_upper").returns("ABC"));
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let batch = client_handle.batch();
+    assert!(batch.is_empty());
+    runtime.block_on(batch.send()).unwrap();
+
+    // Nothing was sent for the empty batch, so the only call the server sees is this one.
+    let call = client_handle.call_method::<String>("to_upper", &["abc"]);
+    assert_eq!("ABC", runtime.block_on(call).unwrap());
+    mock.verify().unwrap();
+}
+
+#[test]
+fn batch_with_unserializable_entry() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("to_upper").returns("ABC"));
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut batch = client_handle.batch();
+    // JSON objects can only have strings as keys.
+    let mut unserializable = HashMap::new();
+    unserializable.insert((1, 2), 3);
+    let failed = batch.call_method::<String>("to_upper", &unserializable);
+    let succeeded = batch.call_method::<String>("to_upper", &["abc"]);
+    assert_eq!(1, batch.len());
+    runtime.block_on(batch.send()).unwrap();
+
+    match runtime.block_on(failed).unwrap_err().kind() {
+        ErrorKind::SerializeError => (),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+    assert_eq!("ABC", runtime.block_on(succeeded).unwrap());
+    mock.verify().unwrap();
+}
+
+#[test]
+fn partial_batch_response() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut server) = channel_transport();
+    let (client, client_handle) = transport.into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let mut batch = client_handle.batch();
+    let answered = batch.call_method::<String>("to_upper", &["abc"]);
+    let unanswered = batch.call_method::<String>("to_upper", &["def"]);
+    let invalid = batch.call_method::<String>("to_upper", &["ghi"]);
+    runtime.block_on(batch.send()).unwrap();
+    let request = server.receive(&mut runtime);
+    server.send(json!([
+        {"jsonrpc": "2.0", "result": "ABC", "id": request[0]["id"]},
+        {"result": "GHI", "id": request[2]["id"]},
+    ]));
+
+    assert_eq!("ABC", runtime.block_on(answered).unwrap());
+    match runtime.block_on(unanswered).unwrap_err().kind() {
+        ErrorKind::MissingBatchResponse => (),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+    match runtime.block_on(invalid).unwrap_err().kind() {
+        ErrorKind::InvalidVersion => (),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+
+    // The invalid entry only failed its own call, the client keeps working.
+    let call = client_handle.call_method::<String>("to_upper", &["jkl"]);
+    let call = oneshot::spawn(call, &runtime.handle());
+    let request = server.receive(&mut runtime);
+    server.send(json!({"jsonrpc": "2.0", "result": "JKL", "id": request["id"]}));
+    assert_eq!("JKL", runtime.block_on(call).unwrap());
+}
+
+#[test]
+fn batch_errors_without_id() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut server) = channel_transport();
+    let (client, client_handle) = transport.into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let invalid_request = json!({
+        "jsonrpc": "2.0",
+        "error": {"code": -32600, "message": "Invalid Request"},
+        "id": null,
+    });
+
+    let mut batch = client_handle.batch();
+    let answered = batch.call_method::<String>("to_upper", &["abc"]);
+    let invalid = batch.call_method::<String>("to_upper", &["def"]);
+    runtime.block_on(batch.send()).unwrap();
+    let request = server.receive(&mut runtime);
+    server.send(json!([
+        {"jsonrpc": "2.0", "result": "ABC", "id": request[0]["id"]},
+        invalid_request,
+    ]));
+    assert_eq!("ABC", runtime.block_on(answered).unwrap());
+    match runtime.block_on(invalid).unwrap_err().kind() {
+        ErrorKind::JsonRpcError(error) => assert_eq!(ErrorCode::InvalidRequest, error.code),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+
+    // A response without any ids answers the oldest batch.
+    let mut batch = client_handle.batch();
+    let first = batch.call_method::<String>("to_upper", &["abc"]);
+    let second = batch.call_method::<String>("to_upper", &["def"]);
+    runtime.block_on(batch.send()).unwrap();
+    server.receive(&mut runtime);
+    server.send(json!([invalid_request]));
+    assert!(runtime.block_on(first).is_err());
+    match runtime.block_on(second).unwrap_err().kind() {
+        ErrorKind::JsonRpcError(error) => assert_eq!(ErrorCode::InvalidRequest, error.code),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+}
+
+#[test]
+fn v1_batch() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut server) = channel_transport();
+    let (client, client_handle) = transport.into_client();
+    let client = client.protocol_version(ProtocolVersion::V1);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let mut batch = client_handle.batch();
+    let answered = batch.call_method::<String>("to_upper", &["abc"]);
+    let logged = batch.send_notification("log", &["batched"]);
+    let failed = batch.call_method::<String>("to_upper", &["def"]);
+    runtime.block_on(batch.send()).unwrap();
+    let request = server.receive(&mut runtime);
+    assert_eq!(
+        json!([
+            {"method": "to_upper", "params": ["abc"], "id": request[0]["id"]},
+            {"method": "log", "params": ["batched"], "id": null},
+            {"method": "to_upper", "params": ["def"], "id": request[2]["id"]},
+        ]),
+        request
+    );
+    server.send(json!([
+        {"result": "ABC", "error": null, "id": request[0]["id"]},
+        {"result": null, "error": "Out of cheese", "id": request[2]["id"]},
+    ]));
+
+    assert_eq!("ABC", runtime.block_on(answered).unwrap());
+    runtime.block_on(logged).unwrap();
+    match runtime.block_on(failed).unwrap_err().kind() {
+        ErrorKind::JsonRpcError(error) => assert_eq!("Out of cheese", error.message),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+}
+
+#[test]
+fn notifications_fail_when_transport_rejects_them() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut server) = channel_transport();
+    let (client, client_handle) = transport.into_client();
+    runtime.spawn(client.map_err(|_| ()));
+    server.reject_payloads();
+
+    let mut batch = client_handle.batch();
+    let batched = batch.send_notification("log", &["batched"]);
+    runtime.block_on(batch.send()).unwrap();
+    assert!(runtime.block_on(batched).is_err());
+    let single = client_handle.send_notification("log".to_owned(), &["single"]);
+    assert!(runtime.block_on(single).is_err());
+}
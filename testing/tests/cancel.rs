@@ -0,0 +1,57 @@
+extern crate futures;
+extern crate jsonrpc_client_core;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+mod common;
+
+use common::channel_transport;
+use futures::sync::oneshot;
+use futures::Future;
+use jsonrpc_client_core::cancel::CancelNotification;
+use jsonrpc_client_core::rate_limit::RateLimit;
+use jsonrpc_client_core::Transport;
+use tokio::runtime::current_thread::Runtime;
+
+#[test]
+fn dropped_calls_are_cancelled() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut server) = channel_transport();
+    let (client, client_handle) = transport.into_client();
+    let client = client
+        .cancel_notification(CancelNotification::lsp())
+        .rate_limit(RateLimit::new().max_in_flight(2));
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let call = |runtime: &mut Runtime, string: &str| {
+        let call = client_handle.call_method::<String>("to_upper", &[string]);
+        oneshot::spawn(call, &runtime.handle())
+    };
+    let first = call(&mut runtime, "abc");
+    let first_id = server.receive(&mut runtime)["id"].clone();
+    let second = call(&mut runtime, "def");
+    let second_id = server.receive(&mut runtime)["id"].clone();
+
+    drop(first);
+    drop(second);
+    let mut cancelled_ids = vec![];
+    for _ in 0..2 {
+        let notification = server.receive(&mut runtime);
+        assert_eq!("$/cancelRequest", notification["method"]);
+        cancelled_ids.push(notification["params"]["id"].clone());
+    }
+    cancelled_ids.sort_by_key(|id| id.as_u64());
+    assert_eq!(vec![first_id.clone(), second_id], cancelled_ids);
+
+    // The cancelled calls no longer count as in flight, so both of these are sent right away.
+    let third = call(&mut runtime, "ghi");
+    let third_request = server.receive(&mut runtime);
+    let fourth = call(&mut runtime, "jkl");
+    let fourth_request = server.receive(&mut runtime);
+    server.send(json!({"jsonrpc": "2.0", "result": "ABC", "id": first_id}));
+    server.send(json!({"jsonrpc": "2.0", "result": "GHI", "id": third_request["id"]}));
+    server.send(json!({"jsonrpc": "2.0", "result": "JKL", "id": fourth_request["id"]}));
+    assert_eq!("GHI", runtime.block_on(third).unwrap());
+    assert_eq!("JKL", runtime.block_on(fourth).unwrap());
+}
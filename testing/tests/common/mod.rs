@@ -0,0 +1,77 @@
+#![allow(dead_code)]
+
+use futures::future;
+use futures::sync::mpsc;
+use futures::{Sink, Stream};
+use jsonrpc_client_core::{DuplexTransport, Transport};
+use serde_json::{self, Value as JsonValue};
+use tokio::runtime::current_thread::Runtime;
+
+use std::io;
+
+/// A transport whose server side is played by the test, through the matching `ChannelServer`.
+pub struct ChannelTransport {
+    requests_tx: mpsc::UnboundedSender<String>,
+    responses_rx: mpsc::UnboundedReceiver<String>,
+}
+
+/// The server side of a `ChannelTransport`.
+pub struct ChannelServer {
+    requests_rx: mpsc::UnboundedReceiver<String>,
+    responses_tx: mpsc::UnboundedSender<String>,
+}
+
+pub fn channel_transport() -> (ChannelTransport, ChannelServer) {
+    let (requests_tx, requests_rx) = mpsc::unbounded();
+    let (responses_tx, responses_rx) = mpsc::unbounded();
+    (
+        ChannelTransport {
+            requests_tx,
+            responses_rx,
+        },
+        ChannelServer {
+            requests_rx,
+            responses_tx,
+        },
+    )
+}
+
+impl Transport for ChannelTransport {
+    type Error = io::Error;
+    type Sink = Box<dyn Sink<SinkItem = String, SinkError = io::Error> + Send>;
+    type Stream = Box<dyn Stream<Item = String, Error = io::Error> + Send>;
+
+    fn io_pair(self) -> (Self::Sink, Self::Stream) {
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "Server is gone");
+        (
+            Box::new(self.requests_tx.sink_map_err(move |_| closed())),
+            Box::new(self.responses_rx.map_err(move |_| closed())),
+        )
+    }
+}
+
+impl DuplexTransport for ChannelTransport {}
+
+impl ChannelServer {
+    /// Runs the runtime until the client sends a payload, and returns it.
+    pub fn receive(&mut self, runtime: &mut Runtime) -> JsonValue {
+        let requests_rx = &mut self.requests_rx;
+        let payload = runtime
+            .block_on(future::poll_fn(|| requests_rx.poll()))
+            .unwrap()
+            .expect("The client is gone");
+        serde_json::from_str(&payload).unwrap()
+    }
+
+    /// Rejects all further payloads of the client, while keeping the connection open.
+    pub fn reject_payloads(&mut self) {
+        self.requests_rx.close();
+    }
+
+    /// Sends a payload to the client.
+    pub fn send(&self, payload: JsonValue) {
+        self.responses_tx
+            .unbounded_send(payload.to_string())
+            .unwrap();
+    }
+}
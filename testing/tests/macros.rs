@@ -0,0 +1,302 @@
+extern crate futures;
+#[macro_use]
+extern crate jsonrpc_client_core;
+extern crate jsonrpc_client_testing;
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+use futures::sync::mpsc;
+use futures::{Future, Stream};
+use jsonrpc_client_core::rpc_error::{ErrorCode, RpcError};
+use jsonrpc_client_core::server::types::Output;
+use jsonrpc_client_core::server::Server;
+use jsonrpc_client_core::{Client, RpcFuture, Transport};
+use jsonrpc_client_testing::{Expectation, MockTransport};
+use serde::de::DeserializeOwned;
+use tokio::runtime::current_thread::Runtime;
+
+jsonrpc_client!(pub struct AccountClient {
+    #[omit_trailing_none]
+    pub fn balance(&mut self, account: &str, block: Option<u64>) -> Future<u64>;
+
+    pub fn block_hash(&mut self, height: Option<u64>) -> Future<String>;
+
+    #[named_params(account = "accountId")]
+    pub fn transfer(&mut self, account: &str, amount: u64, memo: Option<&str>) -> Future<()>;
+
+    #[named_params]
+    pub fn history(&mut self, account: Option<&str>, limit: Option<u64>) -> Future<Vec<u64>>;
+});
+
+jsonrpc_client!(
+    /// A client passing the arguments of all its methods by name.
+    #[named_params]
+    pub struct NamedClient {
+        pub fn to_upper(&mut self, string: &str) -> Future<String>;
+        pub fn nullary(&mut self) -> Future<()>;
+    }
+);
+
+jsonrpc_client!(
+    /// A client for methods in the `storage` namespace.
+    #[rpc(namespace = "storage")]
+    pub struct StorageClient {
+        pub fn list(&mut self) -> Future<Vec<String>>;
+
+        #[rpc(name = "$/ping")]
+        pub fn ping(&mut self) -> Future<()>;
+
+        pub notify fn changed(&mut self, key: &str);
+
+        #[named_params]
+        pub notify fn removed(&mut self, key: &str, reason: Option<&str>);
+    }
+);
+
+jsonrpc_client!(
+    /// A client that can be shared, as all its methods take `&self`.
+    pub(crate) struct SettingsClient {
+        pub fn get<T: DeserializeOwned + Send>(&self, key: &str) -> Future<T>;
+
+        pub(crate) fn set<'a, T>(&self, key: &'a str, value: T) -> Future<()>
+        where
+            T: serde::Serialize + Send + 'a;
+
+        notify fn reload(&self);
+    }
+);
+
+mod scoped {
+    use jsonrpc_client_core::RpcFuture;
+
+    jsonrpc_client!(
+        /// A client whose methods are only visible in this module.
+        pub struct ScopedClient {
+            pub(in ::scoped) fn ping(&self) -> Future<()>;
+        }
+    );
+
+    pub fn ping(client: &ScopedClient) -> RpcFuture<()> {
+        client.ping()
+    }
+}
+
+jsonrpc_server!(
+    /// The calls and notifications a daemon handles.
+    pub trait DaemonCallbacks {
+        fn ping(&self) -> Future<String>;
+
+        fn divide(&self, dividend: u64, divisor: u64) -> Future<u64>;
+
+        #[named_params(factor = "by")]
+        fn scale(&self, value: u64, factor: Option<u64>) -> Future<u64>;
+
+        #[rpc(name = "$/log")]
+        notify fn log(&self, message: String);
+    }
+);
+
+struct Daemon {
+    log_tx: mpsc::UnboundedSender<String>,
+}
+
+impl DaemonCallbacks for Daemon {
+    fn ping(&self) -> RpcFuture<String, RpcError> {
+        RpcFuture::new(futures::future::ok("pong".to_owned()))
+    }
+
+    fn divide(&self, dividend: u64, divisor: u64) -> RpcFuture<u64, RpcError> {
+        let result = dividend
+            .checked_div(divisor)
+            .ok_or_else(|| RpcError::new(ErrorCode::ServerError(-1)));
+        RpcFuture::new(futures::future::result(result))
+    }
+
+    fn scale(&self, value: u64, factor: Option<u64>) -> RpcFuture<u64, RpcError> {
+        RpcFuture::new(futures::future::ok(value * factor.unwrap_or(2)))
+    }
+
+    fn log(&self, message: String) -> RpcFuture<(), RpcError> {
+        let result = self
+            .log_tx
+            .unbounded_send(message)
+            .map_err(|_| RpcError::internal_error());
+        RpcFuture::new(futures::future::result(result))
+    }
+}
+
+#[test]
+fn named_params() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(
+        Expectation::method("balance")
+            .params(json!(["alice"]))
+            .returns(5),
+    );
+    mock.expect(
+        Expectation::method("balance")
+            .params(json!(["alice", 7]))
+            .returns(3),
+    );
+    mock.expect(
+        Expectation::method("block_hash")
+            .params(json!([null]))
+            .returns("00ff"),
+    );
+    mock.expect(Expectation::method("transfer").params(json!({"accountId": "alice", "amount": 5})));
+    mock.expect(
+        Expectation::method("transfer")
+            .params(json!({"accountId": "bob", "amount": 2, "memo": "rent"})),
+    );
+    mock.expect(
+        Expectation::method("history")
+            .params(json!({"account": null, "limit": 2}))
+            .returns(json!([1, 2])),
+    );
+    mock.expect(
+        Expectation::method("history")
+            .params(json!({"account": "alice"}))
+            .returns(json!([3])),
+    );
+    mock.expect(
+        Expectation::method("to_upper")
+            .params(json!({"string": "abc"}))
+            .returns("ABC"),
+    );
+    mock.expect(Expectation::method("nullary"));
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut client = AccountClient::new(client_handle.clone());
+    assert_eq!(5, runtime.block_on(client.balance("alice", None)).unwrap());
+    assert_eq!(
+        3,
+        runtime.block_on(client.balance("alice", Some(7))).unwrap()
+    );
+    assert_eq!("00ff", runtime.block_on(client.block_hash(None)).unwrap());
+    runtime.block_on(client.transfer("alice", 5, None)).unwrap();
+    runtime
+        .block_on(client.transfer("bob", 2, Some("rent")))
+        .unwrap();
+    assert_eq!(
+        vec![1, 2],
+        runtime.block_on(client.history(None, Some(2))).unwrap()
+    );
+    assert_eq!(
+        vec![3],
+        runtime
+            .block_on(client.history(Some("alice"), None))
+            .unwrap()
+    );
+
+    let mut client = NamedClient::new(client_handle);
+    assert_eq!("ABC", runtime.block_on(client.to_upper("abc")).unwrap());
+    runtime.block_on(client.nullary()).unwrap();
+    mock.verify().unwrap();
+}
+
+#[test]
+fn method_names() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("storage.list").returns(json!(["a"])));
+    mock.expect(Expectation::method("$/ping"));
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut client = StorageClient::new(client_handle);
+    assert_eq!(vec!["a"], runtime.block_on(client.list()).unwrap());
+    runtime.block_on(client.ping()).unwrap();
+    mock.verify().unwrap();
+}
+
+#[test]
+fn notifications() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("storage.changed").params(json!(["a"])));
+    mock.expect(Expectation::method("storage.removed").params(json!({"key": "a"})));
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut client = StorageClient::new(client_handle);
+    runtime.block_on(client.changed("a")).unwrap();
+    runtime.block_on(client.removed("a", None)).unwrap();
+    mock.verify().unwrap();
+}
+
+#[test]
+fn generic_methods() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("set").params(json!(["retries", 3])));
+    mock.expect(
+        Expectation::method("get")
+            .params(json!(["retries"]))
+            .returns(3),
+    );
+    mock.expect(Expectation::method("reload"));
+    mock.expect(Expectation::method("ping"));
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let client = SettingsClient::new(client_handle.clone());
+    runtime.block_on(client.set("retries", 3)).unwrap();
+    let retries: u32 = runtime.block_on(client.get("retries")).unwrap();
+    assert_eq!(3, retries);
+    runtime.block_on(client.reload()).unwrap();
+    let scoped = scoped::ScopedClient::new(client_handle);
+    runtime.block_on(scoped::ping(&scoped)).unwrap();
+    mock.verify().unwrap();
+}
+
+#[test]
+fn server_callbacks() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    let (log_tx, log_rx) = mpsc::unbounded();
+    let (mut server, _server_handle) = Server::new();
+    Daemon { log_tx }.register_handlers(&mut server).unwrap();
+    let (client, _client_handle) = Client::with_server(mock.clone(), server);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let mut call = |method, params| runtime.block_on(mock.send_request(method, params)).unwrap();
+    match call("ping", json!([])) {
+        Output::Success(success) => assert_eq!(success.result, "pong"),
+        Output::Failure(failure) => panic!("Unexpected failure: {:?}", failure),
+    }
+    match call("divide", json!([6, 3])) {
+        Output::Success(success) => assert_eq!(success.result, 2),
+        Output::Failure(failure) => panic!("Unexpected failure: {:?}", failure),
+    }
+    match call("divide", json!([6, 0])) {
+        Output::Failure(failure) => assert_eq!(failure.error.code, ErrorCode::ServerError(-1)),
+        Output::Success(success) => panic!("Unexpected success: {:?}", success),
+    }
+    match call("divide", json!([6])) {
+        Output::Failure(failure) => assert_eq!(failure.error.code, ErrorCode::InvalidParams),
+        Output::Success(success) => panic!("Unexpected success: {:?}", success),
+    }
+    match call("scale", json!({"value": 3, "by": 4})) {
+        Output::Success(success) => assert_eq!(success.result, 12),
+        Output::Failure(failure) => panic!("Unexpected failure: {:?}", failure),
+    }
+    match call("scale", json!({"value": 3})) {
+        Output::Success(success) => assert_eq!(success.result, 6),
+        Output::Failure(failure) => panic!("Unexpected failure: {:?}", failure),
+    }
+    match call("scale", json!([3, 4])) {
+        Output::Failure(failure) => assert_eq!(failure.error.code, ErrorCode::InvalidParams),
+        Output::Success(success) => panic!("Unexpected success: {:?}", success),
+    }
+
+    mock.send_notification("$/log", json!(["started"])).unwrap();
+    let (message, _) = runtime
+        .block_on(log_rx.into_future().map_err(|_| ()))
+        .unwrap();
+    assert_eq!(Some("started".to_owned()), message);
+    mock.verify().unwrap();
+}
@@ -0,0 +1,163 @@
+extern crate futures;
+extern crate jsonrpc_client_core;
+extern crate jsonrpc_client_testing;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+use futures::Future;
+use jsonrpc_client_core::middleware::{CallAction, Middleware, MiddlewareFuture};
+use jsonrpc_client_core::server::types::{MethodCall, Notification, Output, Success};
+use jsonrpc_client_core::{Error, ErrorKind, Transport};
+use jsonrpc_client_testing::{Expectation, MockTransport};
+use serde_json::Value as JsonValue;
+use tokio::runtime::current_thread::Runtime;
+use tokio::timer::Delay;
+
+use std::time::{Duration, Instant};
+
+/// Moves all calls and notifications to a versioned namespace, and rejects calls to `forbidden`.
+struct Versioned;
+
+impl Middleware for Versioned {
+    fn method_call(&self, mut call: MethodCall) -> MiddlewareFuture<CallAction> {
+        if call.method == "forbidden" {
+            return Box::new(futures::future::err(Error::from("Forbidden method")));
+        }
+        call.method = format!("v2.{}", call.method);
+        Box::new(futures::future::ok(CallAction::Send(call)))
+    }
+
+    fn notification(
+        &self,
+        mut notification: Notification,
+    ) -> MiddlewareFuture<Option<Notification>> {
+        if notification.method == "dropped" {
+            return Box::new(futures::future::ok(None));
+        }
+        notification.method = format!("v2.{}", notification.method);
+        Box::new(futures::future::ok(Some(notification)))
+    }
+}
+
+/// Answers calls to `cached` without sending them to the server.
+struct Cache;
+
+impl Middleware for Cache {
+    fn method_call(&self, call: MethodCall) -> MiddlewareFuture<CallAction> {
+        if call.method != "v2.cached" {
+            return Box::new(futures::future::ok(CallAction::Send(call)));
+        }
+        Box::new(futures::future::ok(CallAction::Respond(Output::Success(
+            Success {
+                jsonrpc: call.jsonrpc,
+                result: json!("from cache"),
+                id: call.id,
+            },
+        ))))
+    }
+}
+
+/// Wraps every successful result in an object, after waiting a while.
+struct SlowWrapper;
+
+impl Middleware for SlowWrapper {
+    fn response(&self, output: Output) -> MiddlewareFuture<Output> {
+        let wrapped = Delay::new(Instant::now() + Duration::from_millis(20))
+            .map_err(|e| panic!("Timer failed: {}", e))
+            .map(|()| match output {
+                Output::Success(mut success) => {
+                    success.result = json!({ "wrapped": success.result });
+                    Output::Success(success)
+                }
+                output => output,
+            });
+        Box::new(wrapped)
+    }
+}
+
+#[test]
+fn rewrites_and_answers_calls() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(
+        Expectation::method("v2.to_upper")
+            .params(json!(["abc"]))
+            .returns("ABC"),
+    );
+    mock.expect(Expectation::method("v2.log"));
+
+    let (client, client_handle) = mock.clone().into_client();
+    let client = client.middleware(Versioned).middleware(Cache);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let call = client_handle.call_method::<String>("to_upper", &["abc"]);
+    assert_eq!("ABC", runtime.block_on(call).unwrap());
+    let cached = client_handle.call_method::<String>("cached", &());
+    assert_eq!("from cache", runtime.block_on(cached).unwrap());
+    let forbidden = client_handle.call_method::<String>("forbidden", &());
+    match runtime.block_on(forbidden).unwrap_err().kind() {
+        ErrorKind::Msg(message) => assert_eq!("Forbidden method", message),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+    let log = client_handle.send_notification("log".to_owned(), &());
+    runtime.block_on(log).unwrap();
+    let dropped = client_handle.send_notification("dropped".to_owned(), &());
+    runtime.block_on(dropped).unwrap();
+    mock.verify().unwrap();
+}
+
+#[test]
+fn rewrites_responses() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("v2.to_upper").returns("ABC"));
+
+    let (client, client_handle) = mock.clone().into_client();
+    // Answers of the cache pass back through the layers before it, just like responses.
+    let client = client
+        .middleware(SlowWrapper)
+        .middleware(Versioned)
+        .middleware(Cache);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let call = client_handle.call_method::<JsonValue>("to_upper", &["abc"]);
+    assert_eq!(json!({"wrapped": "ABC"}), runtime.block_on(call).unwrap());
+    let cached = client_handle.call_method::<JsonValue>("cached", &());
+    assert_eq!(
+        json!({"wrapped": "from cache"}),
+        runtime.block_on(cached).unwrap()
+    );
+    mock.verify().unwrap();
+}
+
+#[test]
+fn slow_middleware_with_batch() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(
+        Expectation::method("to_upper")
+            .params(json!(["abc"]))
+            .returns("ABC"),
+    );
+    mock.expect(
+        Expectation::method("to_upper")
+            .params(json!(["def"]))
+            .returns("DEF"),
+    );
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(
+        client
+            .middleware(SlowWrapper)
+            .map_err(|e| panic!("Client failed: {}", e)),
+    );
+
+    let mut batch = client_handle.batch();
+    let first = batch.call_method::<JsonValue>("to_upper", &["abc"]);
+    let second = batch.call_method::<JsonValue>("to_upper", &["def"]);
+    runtime.block_on(batch.send()).unwrap();
+    assert_eq!(json!({"wrapped": "ABC"}), runtime.block_on(first).unwrap());
+    assert_eq!(json!({"wrapped": "DEF"}), runtime.block_on(second).unwrap());
+    mock.verify().unwrap();
+}
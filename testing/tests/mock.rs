@@ -0,0 +1,155 @@
+extern crate futures;
+#[macro_use]
+extern crate jsonrpc_client_core;
+extern crate jsonrpc_client_testing;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+use futures::sync::mpsc;
+use futures::{Future, Stream};
+use jsonrpc_client_core::server::types::{
+    Error as RpcError, ErrorCode, MethodCall, Output, Success, Version,
+};
+use jsonrpc_client_core::server::{Handler, Server};
+use jsonrpc_client_core::{Client, Transport};
+use jsonrpc_client_testing::{ErrorKind, Expectation, MockTransport};
+use tokio::runtime::current_thread::Runtime;
+
+use std::time::{Duration, Instant};
+
+jsonrpc_client!(pub struct UpperClient {
+    pub fn to_upper(&mut self, string: &str) -> Future<String>;
+    pub fn sleep(&mut self, millis: u64) -> Future<()>;
+});
+
+#[test]
+fn scripted_calls() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+    mock.expect(
+        Expectation::method("to_upper")
+            .params(json!(["abc"]))
+            .returns("ABC"),
+    );
+    mock.expect(
+        Expectation::method("to_upper")
+            .params_matching(|params| params[0] == "fail")
+            .fails_with(RpcError::new(ErrorCode::InternalError)),
+    );
+    mock.expect(Expectation::method("sleep").after(Duration::from_millis(50)));
+
+    let (client, client_handle) = mock.clone().into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut client = UpperClient::new(client_handle);
+
+    assert_eq!("ABC", runtime.block_on(client.to_upper("abc")).unwrap());
+    assert!(runtime.block_on(client.to_upper("fail")).is_err());
+    let start = Instant::now();
+    runtime.block_on(client.sleep(50)).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    mock.verify().unwrap();
+
+    // Unexpected calls fail, and so does verifying the mock afterwards.
+    assert!(runtime.block_on(client.to_upper("abc")).is_err());
+    match mock.verify() {
+        Err(ref e) => match e.kind() {
+            ErrorKind::ExpectationsNotMet(problems) => assert!(problems.contains("to_upper")),
+            kind => panic!("Unexpected error: {}", kind),
+        },
+        Ok(()) => panic!("Unexpected call not reported"),
+    }
+}
+
+#[test]
+fn missed_expectations() {
+    let mock = MockTransport::new();
+    mock.expect(Expectation::method("to_upper").times(2));
+    assert!(mock.verify().is_err());
+}
+
+#[test]
+fn requests_to_client() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+
+    let (server, server_handle) = Server::new();
+    let (client, _client_handle) = Client::with_server(mock.clone(), server);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let ping = Handler::Method(Box::new(|call: MethodCall| {
+        Box::new(futures::future::ok(Output::Success(Success {
+            jsonrpc: Some(Version::V2),
+            result: json!("pong"),
+            id: call.id,
+        })))
+    }));
+    runtime
+        .block_on(server_handle.add("ping".to_owned(), ping))
+        .unwrap();
+
+    match runtime
+        .block_on(mock.send_request("ping", json!([])))
+        .unwrap()
+    {
+        Output::Success(success) => assert_eq!(success.result, "pong"),
+        Output::Failure(failure) => panic!("Unexpected failure: {:?}", failure),
+    }
+    mock.verify().unwrap();
+}
+
+#[test]
+fn typed_handlers() {
+    let mut runtime = Runtime::new().unwrap();
+    let mock = MockTransport::new();
+
+    let (server, server_handle) = Server::new();
+    let (client, _client_handle) = Client::with_server(mock.clone(), server);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let add = server_handle.add_method("add".to_owned(), |(a, b): (u64, u64)| {
+        a.checked_add(b)
+            .ok_or_else(|| RpcError::new(ErrorCode::ServerError(-1)))
+    });
+    runtime.block_on(add).unwrap();
+    let divide = server_handle.add_method("divide".to_owned(), |(a, b): (u64, u64)| {
+        a.checked_div(b)
+            .ok_or_else(|| format!("Can't divide {} by zero", a))
+    });
+    runtime.block_on(divide).unwrap();
+    let (news_tx, news_rx) = mpsc::unbounded();
+    let news = server_handle.add_notification("news".to_owned(), move |(news,): (String,)| {
+        news_tx
+            .unbounded_send(news)
+            .map_err(|_| RpcError::internal_error())
+    });
+    runtime.block_on(news).unwrap();
+
+    let mut call = |params| runtime.block_on(mock.send_request("add", params)).unwrap();
+    match call(json!([1, 2])) {
+        Output::Success(success) => assert_eq!(success.result, 3),
+        Output::Failure(failure) => panic!("Unexpected failure: {:?}", failure),
+    }
+    match call(json!(["one", 2])) {
+        Output::Failure(failure) => assert_eq!(failure.error.code, ErrorCode::InvalidParams),
+        Output::Success(success) => panic!("Unexpected success: {:?}", success),
+    }
+    match call(json!([u64::max_value(), 1])) {
+        Output::Failure(failure) => assert_eq!(failure.error.code, ErrorCode::ServerError(-1)),
+        Output::Success(success) => panic!("Unexpected success: {:?}", success),
+    }
+    let divided = runtime.block_on(mock.send_request("divide", json!([1, 0])));
+    match divided.unwrap() {
+        Output::Failure(failure) => {
+            assert_eq!(failure.error.code, ErrorCode::InternalError);
+            assert_eq!(failure.error.message, "Can't divide 1 by zero");
+        }
+        Output::Success(success) => panic!("Unexpected success: {:?}", success),
+    }
+
+    mock.send_notification("news", json!([5])).unwrap();
+    mock.send_notification("news", json!(["extra"])).unwrap();
+    let (news, _) = runtime
+        .block_on(news_rx.into_future().map_err(|_| ()))
+        .unwrap();
+    assert_eq!(Some("extra".to_owned()), news);
+    mock.verify().unwrap();
+}
@@ -0,0 +1,76 @@
+extern crate futures;
+extern crate jsonrpc_client_core;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+mod common;
+
+use common::channel_transport;
+use futures::sync::oneshot;
+use futures::Future;
+use jsonrpc_client_core::rate_limit::RateLimit;
+use jsonrpc_client_core::Transport;
+use tokio::runtime::current_thread::Runtime;
+use tokio::timer::Delay;
+
+use std::time::{Duration, Instant};
+
+#[test]
+fn spaces_out_requests() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut server) = channel_transport();
+    let (client, client_handle) = transport.into_client();
+    let client = client.rate_limit(RateLimit::new().requests_per_second(20).burst(1));
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let calls: Vec<_> = ["abc", "def", "ghi"]
+        .iter()
+        .map(|string| {
+            let call = client_handle.call_method::<String>("to_upper", &[string]);
+            oneshot::spawn(call, &runtime.handle())
+        })
+        .collect();
+    let mut received_at = vec![];
+    for _ in 0..3 {
+        let request = server.receive(&mut runtime);
+        received_at.push(Instant::now());
+        let result = request["params"][0].as_str().unwrap().to_uppercase();
+        server.send(json!({"jsonrpc": "2.0", "result": result, "id": request["id"]}));
+    }
+    for window in received_at.windows(2) {
+        assert!(window[1] - window[0] >= Duration::from_millis(45));
+    }
+    let results: Vec<_> = calls
+        .into_iter()
+        .map(|call| runtime.block_on(call).unwrap())
+        .collect();
+    assert_eq!(vec!["ABC", "DEF", "GHI"], results);
+}
+
+#[test]
+fn held_back_calls_do_not_time_out() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut server) = channel_transport();
+    let (client, client_handle) = transport.into_client();
+    let client = client.rate_limit(RateLimit::new().max_in_flight(1));
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let first = client_handle.call_method::<String>("to_upper", &["abc"]);
+    let first = oneshot::spawn(first, &runtime.handle());
+    let first_request = server.receive(&mut runtime);
+    let timeout = Duration::from_millis(50);
+    let second = client_handle.call_method_with_timeout::<String>("to_upper", &["def"], timeout);
+    let second = oneshot::spawn(second, &runtime.handle());
+
+    // The second call waits for the first one for longer than its timeout.
+    runtime
+        .block_on(Delay::new(Instant::now() + Duration::from_millis(100)))
+        .unwrap();
+    server.send(json!({"jsonrpc": "2.0", "result": "ABC", "id": first_request["id"]}));
+    assert_eq!("ABC", runtime.block_on(first).unwrap());
+    let second_request = server.receive(&mut runtime);
+    assert_eq!(json!(["def"]), second_request["params"]);
+    server.send(json!({"jsonrpc": "2.0", "result": "DEF", "id": second_request["id"]}));
+    assert_eq!("DEF", runtime.block_on(second).unwrap());
+}
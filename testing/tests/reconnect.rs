@@ -0,0 +1,196 @@
+extern crate futures;
+extern crate jsonrpc_client_core;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+mod common;
+
+use common::{channel_transport, ChannelServer, ChannelTransport};
+use futures::sync::{mpsc, oneshot};
+use futures::{future, Future, Stream};
+use jsonrpc_client_core::backoff::FixedBackoff;
+use jsonrpc_client_core::protocol::ProtocolVersion;
+use jsonrpc_client_core::reconnect::ReconnectingTransport;
+use jsonrpc_client_core::{ClientHandle, ErrorKind, Transport};
+use tokio::runtime::current_thread::Runtime;
+
+use std::io;
+use std::time::Duration;
+
+/// Returns a transport opening a new channel for every connection, and the receiver of the server
+/// sides of these channels. Connecting fails once `connections` connections have been opened.
+fn reconnecting_transport(
+    connections: usize,
+) -> (
+    ReconnectingTransport<impl FnMut() -> io::Result<ChannelTransport> + Send, FixedBackoff>,
+    mpsc::UnboundedReceiver<ChannelServer>,
+) {
+    let (servers_tx, servers_rx) = mpsc::unbounded();
+    let mut opened = 0;
+    let connect = move || {
+        if opened == connections {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "Server is down",
+            ));
+        }
+        opened += 1;
+        let (transport, server) = channel_transport();
+        servers_tx.unbounded_send(server).unwrap();
+        Ok(transport)
+    };
+    let backoff = FixedBackoff::new(Duration::from_millis(1)).max_attempts(3);
+    (ReconnectingTransport::new(connect, backoff), servers_rx)
+}
+
+/// Runs the runtime until the client opens a new connection, and returns its server side.
+fn next_server(
+    runtime: &mut Runtime,
+    servers_rx: &mut mpsc::UnboundedReceiver<ChannelServer>,
+) -> ChannelServer {
+    runtime
+        .block_on(future::poll_fn(|| servers_rx.poll()))
+        .unwrap()
+        .expect("The transport is gone")
+}
+
+fn call(
+    runtime: &mut Runtime,
+    client_handle: &ClientHandle,
+    method: &str,
+) -> oneshot::SpawnHandle<String, jsonrpc_client_core::Error> {
+    let call = client_handle.call_method::<String>(method.to_owned(), &());
+    oneshot::spawn(call, &runtime.handle())
+}
+
+#[test]
+fn reconnects_after_connection_drop() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut servers_rx) = reconnecting_transport(2);
+    let (client, client_handle) = transport.into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let lost = call(&mut runtime, &client_handle, "lost");
+    let mut server = next_server(&mut runtime, &mut servers_rx);
+    assert_eq!("lost", server.receive(&mut runtime)["method"]);
+    drop(server);
+    match runtime.block_on(lost).unwrap_err().kind() {
+        ErrorKind::ConnectionLost => (),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+
+    let answered = call(&mut runtime, &client_handle, "answered");
+    let mut server = next_server(&mut runtime, &mut servers_rx);
+    let request = server.receive(&mut runtime);
+    assert_eq!("answered", request["method"]);
+    server.send(json!({"jsonrpc": "2.0", "result": "ok", "id": request["id"]}));
+    assert_eq!("ok", runtime.block_on(answered).unwrap());
+}
+
+#[test]
+fn server_errors_are_not_taken_for_lost_connections() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut servers_rx) = reconnecting_transport(1);
+    let (client, client_handle) = transport.into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let failed = call(&mut runtime, &client_handle, "failed");
+    let mut server = next_server(&mut runtime, &mut servers_rx);
+    let request = server.receive(&mut runtime);
+    server.send(json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": -32603,
+            "message": "The connection to the server was lost",
+            "data": "jsonrpc-client-core: connection lost",
+        },
+        "id": request["id"],
+    }));
+    match runtime.block_on(failed).unwrap_err().kind() {
+        ErrorKind::JsonRpcError(_) => (),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+}
+
+#[test]
+fn replays_calls_in_send_order() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut servers_rx) = reconnecting_transport(2);
+    let transport = transport.replay_calls(|call| call.method != "not_replayed");
+    let (client, client_handle) = transport.into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let mut server = next_server(&mut runtime, &mut servers_rx);
+    let mut calls = vec![];
+    for method in &["first", "not_replayed", "second", "third"] {
+        calls.push(call(&mut runtime, &client_handle, method));
+        assert_eq!(*method, server.receive(&mut runtime)["method"]);
+    }
+    drop(server);
+
+    let mut server = next_server(&mut runtime, &mut servers_rx);
+    for method in &["first", "second", "third"] {
+        let request = server.receive(&mut runtime);
+        assert_eq!(*method, request["method"]);
+        server.send(json!({"jsonrpc": "2.0", "result": method, "id": request["id"]}));
+    }
+    let results: Vec<_> = calls
+        .into_iter()
+        .map(|call| runtime.block_on(call).map_err(|e| e.to_string()))
+        .collect();
+    assert_eq!(
+        vec![
+            Ok("first".to_owned()),
+            Err(ErrorKind::ConnectionLost.to_string()),
+            Ok("second".to_owned()),
+            Ok("third".to_owned()),
+        ],
+        results
+    );
+}
+
+#[test]
+fn does_not_replay_v1_notifications() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut servers_rx) = reconnecting_transport(2);
+    let transport = transport.replay_calls(|_| true);
+    let (client, client_handle) = transport.into_client();
+    let client = client.protocol_version(ProtocolVersion::V1);
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+
+    let mut server = next_server(&mut runtime, &mut servers_rx);
+    let notification = client_handle.send_notification("log".to_owned(), &());
+    runtime.block_on(notification).unwrap();
+    assert_eq!(json!(null), server.receive(&mut runtime)["id"]);
+    let replayed = call(&mut runtime, &client_handle, "replayed");
+    assert_eq!("replayed", server.receive(&mut runtime)["method"]);
+    drop(server);
+
+    // Only the call is sent again, the notification was never waiting for a response.
+    let mut server = next_server(&mut runtime, &mut servers_rx);
+    let request = server.receive(&mut runtime);
+    assert_eq!("replayed", request["method"]);
+    server.send(json!({"result": "ok", "error": null, "id": request["id"]}));
+    assert_eq!("ok", runtime.block_on(replayed).unwrap());
+}
+
+#[test]
+fn gives_up_reconnecting() {
+    let mut runtime = Runtime::new().unwrap();
+    let (transport, mut servers_rx) = reconnecting_transport(1);
+    let (client, client_handle) = transport.into_client();
+    let client = oneshot::spawn(client, &runtime.handle());
+
+    let lost = call(&mut runtime, &client_handle, "lost");
+    let mut server = next_server(&mut runtime, &mut servers_rx);
+    server.receive(&mut runtime);
+    drop(server);
+
+    // Every attempt at connecting again is refused, until the backoff policy gives up.
+    match runtime.block_on(client).unwrap_err().kind() {
+        ErrorKind::TransportError => (),
+        kind => panic!("Unexpected error: {}", kind),
+    }
+    assert!(runtime.block_on(lost).is_err());
+}
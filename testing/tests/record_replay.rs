@@ -0,0 +1,91 @@
+extern crate futures;
+#[macro_use]
+extern crate jsonrpc_client_core;
+extern crate jsonrpc_client_testing;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio;
+
+use futures::sync::mpsc;
+use futures::{Future, Sink, Stream};
+use jsonrpc_client_core::Transport;
+use jsonrpc_client_testing::{RecordingTransport, ReplayTransport};
+use serde_json::Value as JsonValue;
+use tokio::runtime::current_thread::Runtime;
+
+use std::io;
+
+jsonrpc_client!(pub struct UpperClient {
+    pub fn to_upper(&mut self, string: &str) -> Future<String>;
+});
+
+/// A transport connected to a server answering `to_upper` calls, running on the given runtime.
+struct UpperServerTransport {
+    requests_tx: mpsc::UnboundedSender<String>,
+    responses_rx: mpsc::UnboundedReceiver<String>,
+}
+
+impl UpperServerTransport {
+    fn spawn(runtime: &mut Runtime) -> Self {
+        let (requests_tx, requests_rx) = mpsc::unbounded::<String>();
+        let (responses_tx, responses_rx) = mpsc::unbounded();
+        runtime.spawn(requests_rx.for_each(move |request| {
+            let request: JsonValue = serde_json::from_str(&request).unwrap();
+            let response = json!({
+                "jsonrpc": "2.0",
+                "result": request["params"][0].as_str().unwrap().to_uppercase(),
+                "id": request["id"],
+            });
+            responses_tx.unbounded_send(response.to_string()).unwrap();
+            Ok(())
+        }));
+        UpperServerTransport {
+            requests_tx,
+            responses_rx,
+        }
+    }
+}
+
+impl Transport for UpperServerTransport {
+    type Error = io::Error;
+    type Sink = Box<dyn Sink<SinkItem = String, SinkError = io::Error> + Send>;
+    type Stream = Box<dyn Stream<Item = String, Error = io::Error> + Send>;
+
+    fn io_pair(self) -> (Self::Sink, Self::Stream) {
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "Server is gone");
+        (
+            Box::new(self.requests_tx.sink_map_err(move |_| closed())),
+            Box::new(self.responses_rx.map_err(move |_| closed())),
+        )
+    }
+}
+
+#[test]
+fn replay_recorded_calls() {
+    let cassette = ::std::env::temp_dir().join(format!(
+        "jsonrpc-client-testing-{}.jsonl",
+        ::std::process::id()
+    ));
+    let mut runtime = Runtime::new().unwrap();
+
+    let transport = UpperServerTransport::spawn(&mut runtime);
+    let transport = RecordingTransport::create(transport, &cassette).unwrap();
+    let (client, client_handle) = transport.into_client();
+    runtime.spawn(client.map_err(|e| panic!("Client failed: {}", e)));
+    let mut client = UpperClient::new(client_handle);
+    assert_eq!("ABC", runtime.block_on(client.to_upper("abc")).unwrap());
+    assert_eq!("DEF", runtime.block_on(client.to_upper("def")).unwrap());
+
+    // Replaying the cassette with a new client, which starts over with the same ids, so the calls
+    // are made in a different order to make sure the responses don't just match by id.
+    let transport = ReplayTransport::open(&cassette).unwrap();
+    let (client, client_handle) = transport.into_client();
+    runtime.spawn(client.map_err(|_| ()));
+    let mut client = UpperClient::new(client_handle);
+    assert_eq!("DEF", runtime.block_on(client.to_upper("def")).unwrap());
+    assert_eq!("ABC", runtime.block_on(client.to_upper("abc")).unwrap());
+
+    // There is no recorded exchange for this call.
+    assert!(runtime.block_on(client.to_upper("ghi")).is_err());
+    ::std::fs::remove_file(&cassette).unwrap();
+}
@@ -43,7 +43,9 @@ where
     S1: Stream,
     S2: Stream<Item = S1::Item, Error = S1::Error>,
 {
-    fn check_weak(&mut self) -> Poll<Option<S1::Item>, S1::Error> {
+    /// Polls only the weak stream, leaving the items of the strong stream where they are. Never
+    /// finishes, not even when the weak stream does.
+    pub fn poll_weak(&mut self) -> Poll<Option<S1::Item>, S1::Error> {
         if self.weak_done {
             return Ok(Async::NotReady);
         };
@@ -68,14 +70,14 @@ where
     fn poll(&mut self) -> Poll<Option<S1::Item>, S1::Error> {
         if !self.use_strong {
             self.use_strong = true;
-            match self.check_weak() {
+            match self.poll_weak() {
                 Ok(Async::NotReady) => self.strong.poll(),
                 other => other,
             }
         } else {
             self.use_strong = false;
             match self.strong.poll() {
-                Ok(Async::NotReady) => self.check_weak(),
+                Ok(Async::NotReady) => self.poll_weak(),
                 other => other,
             }
         }